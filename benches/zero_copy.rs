@@ -0,0 +1,129 @@
+//! Compares the buffered `C3dReader` against lazy `C3dSlice` views on a synthetic file.
+//!
+//! run with `cargo bench --bench zero_copy`.
+use c3d_rs::{
+    AnalogData, C3dAdapter, C3dSlice, C3dWriter, HeaderBlock, ParamData, ParameterBlock,
+    ParameterFormat, Point, PointData,
+};
+use std::hint::black_box;
+use std::io::Cursor;
+use std::time::{Duration, Instant};
+
+const FRAMES: u32 = 1_000_000;
+const MARKERS: usize = 8;
+const CHANNELS: usize = 8;
+const SUBFRAMES: usize = 4;
+
+/// a float file with `FRAMES` frames, the frame count is kept in the TRIAL group.
+fn synthetic_file() -> Vec<u8> {
+    let mut header = HeaderBlock::default();
+    header.point_counts = MARKERS as u16;
+    header.analog_counts = (CHANNELS * SUBFRAMES) as u16;
+    header.frame_first = 1;
+    header.frame_last = u16::MAX;
+    header.scale = -1.0;
+    header.analog_per_frame = SUBFRAMES as u16;
+    header.frame_rate = 100.0;
+
+    let mut parameter = ParameterBlock::new();
+    parameter
+        .insert("POINT", ParameterFormat::scalar("DATA_START", 0_i16))
+        .unwrap();
+    parameter
+        .insert("ANALOG", ParameterFormat::scalar("USED", CHANNELS as i16))
+        .unwrap();
+    parameter
+        .insert(
+            "TRIAL",
+            ParameterFormat::new("ACTUAL_START_FIELD", vec![2], ParamData::Int16(vec![1, 0])),
+        )
+        .unwrap();
+    parameter
+        .insert(
+            "TRIAL",
+            ParameterFormat::new(
+                "ACTUAL_END_FIELD",
+                vec![2],
+                ParamData::Int16(vec![(FRAMES & 0xffff) as u16 as i16, (FRAMES >> 16) as i16]),
+            ),
+        )
+        .unwrap();
+
+    let mut writer = C3dWriter::new(Cursor::new(vec![]), header, parameter).unwrap();
+    writer
+        .write_frames((0..FRAMES).map(|i| {
+            let points = PointData {
+                values: (0..MARKERS)
+                    .map(|m| Point {
+                        x: i as f32,
+                        y: m as f32,
+                        z: 1.0,
+                        residual: Some(0.0),
+                        ..Default::default()
+                    })
+                    .collect(),
+            };
+            let analog = AnalogData {
+                channels: CHANNELS,
+                subframes: SUBFRAMES,
+                values: vec![i as f32; CHANNELS * SUBFRAMES],
+            };
+            (points, Some(analog))
+        }))
+        .unwrap();
+    writer.finish().unwrap().into_inner()
+}
+
+fn measure(name: &str, mut f: impl FnMut() -> f32) {
+    let runs = 5;
+    let mut total = Duration::default();
+    for _ in 0..runs {
+        let start = Instant::now();
+        black_box(f());
+        total += start.elapsed();
+    }
+    println!("{:<24} {:>10.2?} per pass", name, total / runs);
+}
+
+fn main() {
+    let bytes = synthetic_file();
+    println!(
+        "{} frames, {:.1} MB",
+        FRAMES,
+        bytes.len() as f64 / 1024.0 / 1024.0
+    );
+
+    let adapter = C3dAdapter::new(Cursor::new(&bytes[..]))
+        .unwrap()
+        .construct()
+        .unwrap();
+    let slice = C3dSlice::new(&bytes).unwrap();
+    assert_eq!(slice.frames().len(), FRAMES as usize);
+
+    measure("reader, full frames", || {
+        adapter
+            .reader()
+            .unwrap()
+            .map(|(_, points, analog)| points.values[3].x + analog.unwrap().values[5])
+            .sum()
+    });
+    measure("views, full frames", || {
+        slice
+            .frames()
+            .map(|view| {
+                let (points, analog) = view.decode();
+                points.values[3].x + analog.unwrap().values[5]
+            })
+            .sum()
+    });
+    measure("views, one marker", || {
+        slice
+            .frames()
+            .filter_map(|view| view.point(3))
+            .map(|v| v.x)
+            .sum()
+    });
+    measure("views, one sample", || {
+        slice.frames().filter_map(|view| view.sample(5, 0)).sum()
+    });
+}
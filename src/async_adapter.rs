@@ -0,0 +1,218 @@
+use crate::frame::FrameDecoder;
+use crate::{
+    frame_range, validate, AnalogData, FromReader, HeaderBlock, ParameterBlock, ParserError,
+    PointData,
+};
+use futures_util::stream::{self, Stream};
+use std::io::{Cursor, SeekFrom};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+/// `C3dAdapter` for tokio sources, nothing blocks the runtime while parsing or reading frames.
+pub struct AsyncC3dAdapter<T: AsyncRead + AsyncSeek + Unpin> {
+    pub header: Option<HeaderBlock>,
+    pub parameter: Option<ParameterBlock>,
+    handle: T,
+}
+
+impl<T: AsyncRead + AsyncSeek + Unpin> AsyncC3dAdapter<T> {
+    pub async fn new(mut file: T) -> Result<Self, ParserError> {
+        file.seek(SeekFrom::Start(0)).await?;
+
+        Ok(AsyncC3dAdapter {
+            header: None,
+            parameter: None,
+            handle: file,
+        })
+    }
+
+    /// read the header and parameter blocks, then parse them as `C3dAdapter::construct` does.
+    pub async fn construct(mut self) -> Result<Self, ParserError> {
+        let mut bytes = vec![0_u8; 512];
+        self.handle
+            .read_exact(&mut bytes)
+            .await
+            .map_err(|_| ParserError::Truncated {
+                section: "header",
+                offset: 0,
+            })?;
+        let header = HeaderBlock::from_reader(&mut Cursor::new(&bytes[..]))?;
+
+        // the parameter block starts where the header says, its third byte holds its block
+        // count.
+        let block_start = (header.parameter_start as u64).saturating_sub(1).max(1) * 512;
+        self.handle.seek(SeekFrom::Start(block_start)).await?;
+
+        // padded up to the block so that record offsets in errors match the sync parser.
+        let start = block_start as usize;
+        let mut bytes = vec![0_u8; start + 4];
+        self.handle
+            .read_exact(&mut bytes[start..])
+            .await
+            .map_err(|_| ParserError::Truncated {
+                section: "parameter header",
+                offset: block_start,
+            })?;
+
+        let counts = bytes[start + 2] as usize;
+        bytes.resize(start + (counts * 512).max(4), 0);
+        self.handle
+            .read_exact(&mut bytes[start + 4..])
+            .await
+            .map_err(|_| ParserError::Truncated {
+                section: "parameter block",
+                offset: block_start + 4,
+            })?;
+
+        let mut cursor = Cursor::new(&bytes[..]);
+        cursor.set_position(block_start);
+        let parameter = ParameterBlock::from_reader(&mut cursor)?;
+        let header = validate(header, &parameter)?;
+
+        self.header.replace(header);
+        self.parameter.replace(parameter);
+
+        Ok(self)
+    }
+
+    /// frames of the trial in order, a truncated data section ends the stream.
+    pub fn frames(
+        &mut self,
+    ) -> Result<impl Stream<Item = (u32, PointData, Option<AnalogData>)> + Unpin + '_, ParserError>
+    {
+        let header = self.header.as_ref().ok_or(ParserError::MissingField)?;
+        let parameter = self.parameter.as_ref().ok_or(ParserError::MissingField)?;
+
+        let frames = frame_range(header, parameter);
+        let state = FrameState {
+            handle: &mut self.handle,
+            decoder: FrameDecoder::new(header, parameter),
+            buffer: vec![],
+            data_offset: Some((header.data_start as u64).saturating_sub(1) * 512),
+            front: *frames.start() as u64,
+            back: *frames.end() as u64 + 1,
+        };
+
+        // boxed so that callers can poll it without pinning.
+        Ok(Box::pin(stream::unfold(state, |mut state| async move {
+            let frame = state.next().await?;
+            Some((frame, state))
+        })))
+    }
+}
+
+struct FrameState<'a, T> {
+    handle: &'a mut T,
+    decoder: FrameDecoder,
+    buffer: Vec<u8>,
+    // taken by the first read, which seeks to the data section.
+    data_offset: Option<u64>,
+    front: u64,
+    back: u64,
+}
+
+impl<'a, T: AsyncRead + AsyncSeek + Unpin> FrameState<'a, T> {
+    async fn next(&mut self) -> Option<(u32, PointData, Option<AnalogData>)> {
+        if self.front >= self.back {
+            return None;
+        }
+        if let Some(offset) = self.data_offset.take() {
+            self.handle.seek(SeekFrom::Start(offset)).await.ok()?;
+        }
+
+        self.buffer.resize(self.decoder.frame_length(), 0_u8);
+        if self.handle.read_exact(&mut self.buffer[..]).await.is_err() {
+            self.front = self.back;
+            return None;
+        }
+
+        let frame = self.front;
+        self.front += 1;
+        let (points, analog) = self.decoder.decode(&self.buffer);
+        Some((frame as u32, points, analog))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::C3dAdapter;
+    use futures_util::StreamExt;
+
+    #[tokio::test]
+    async fn test_async_adapter() -> anyhow::Result<()> {
+        let file = tokio::fs::File::open("test_data/vicon_trial.c3d").await?;
+        let mut adapter = AsyncC3dAdapter::new(file).await?.construct().await?;
+
+        let file = std::fs::File::open("test_data/vicon_trial.c3d")?;
+        let expected = C3dAdapter::new(file)?.construct()?;
+        assert_eq!(
+            adapter
+                .parameter
+                .as_ref()
+                .unwrap()
+                .get("POINT:LABELS")
+                .unwrap()
+                .as_strings(),
+            expected.get_point_labels()
+        );
+
+        let frames = adapter.frames()?.collect::<Vec<_>>().await;
+        assert_eq!(frames, expected.reader()?.collect::<Vec<_>>());
+
+        // a second pass starts over from the first frame.
+        let first = adapter.frames()?.next().await.unwrap();
+        assert_eq!(first, frames[0]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_parameter_start() -> anyhow::Result<()> {
+        // an empty block between the header and the parameter block.
+        let bytes = std::fs::read("test_data/vicon_trial.c3d")?;
+        let mut moved = bytes[..512].to_vec();
+        moved.extend_from_slice(&[0; 512]);
+        moved.extend_from_slice(&bytes[512..]);
+        moved[0] = 3;
+        let data_start = u16::from_le_bytes([moved[16], moved[17]]) + 1;
+        moved[16..18].copy_from_slice(&data_start.to_le_bytes());
+
+        let mut adapter = AsyncC3dAdapter::new(Cursor::new(moved))
+            .await?
+            .construct()
+            .await?;
+        let expected = C3dAdapter::new(Cursor::new(bytes))?.construct()?;
+        let labels = adapter.parameter.as_ref().unwrap().get("POINT:LABELS");
+        assert_eq!(
+            labels.and_then(|v| v.as_strings()),
+            expected.get_point_labels()
+        );
+        let frames = adapter.frames()?.collect::<Vec<_>>().await;
+        assert_eq!(frames, expected.reader()?.collect::<Vec<_>>());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_truncated_parameter() -> anyhow::Result<()> {
+        let bytes = std::fs::read("test_data/vicon_trial.c3d")?;
+
+        // cut inside the parameter header and inside the records after it.
+        for len in [514, 1000] {
+            let truncated = bytes[..len].to_vec();
+            let error = AsyncC3dAdapter::new(Cursor::new(truncated.clone()))
+                .await?
+                .construct()
+                .await
+                .err()
+                .unwrap();
+            let expected = C3dAdapter::new(Cursor::new(truncated))?
+                .construct()
+                .err()
+                .unwrap();
+            assert_eq!(format!("{:?}", error), format!("{:?}", expected));
+        }
+
+        Ok(())
+    }
+}
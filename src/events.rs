@@ -0,0 +1,426 @@
+use crate::{HeaderBlock, ParamData, ParameterBlock, ParameterFormat, ParserError};
+
+/// key stored in the header when event labels are four characters long.
+const LONG_LABELS_KEY: u16 = 12345;
+
+/// An event marked during the trial, e.g. a heel strike.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Event {
+    pub label: String,
+    /// seconds from the start of the trial.
+    pub time: f32,
+    /// frame the event falls on, the first frame of the trial is 1.
+    pub frame: u32,
+    /// whether the event should be shown on screen.
+    pub display: bool,
+    /// e.g. "Left", "Right" or "General", empty for header events.
+    pub context: String,
+    pub description: String,
+    pub subject: String,
+    pub icon_id: i16,
+    pub generic_flag: i16,
+}
+
+impl Event {
+    pub(crate) fn frame_at(time: f32, frame_rate: f32) -> u32 {
+        // the cast saturates for times far past the trial, including infinity.
+        ((time * frame_rate).round().max(0.0) as u32).saturating_add(1)
+    }
+}
+
+/// the up to 18 events kept in the header by older software.
+pub(crate) fn from_header(header: &HeaderBlock) -> Vec<Event> {
+    let counts = header.event_counts.min(18) as usize;
+    let label_length = if header.event_lables_long == LONG_LABELS_KEY {
+        4
+    } else {
+        2
+    };
+
+    let labels = header.event_labels;
+    let times = header.event_times;
+    let display_flags = header.event_display_flags;
+    let frame_rate = header.frame_rate;
+
+    (0..counts)
+        .map(|i| {
+            let label = labels[i * label_length..(i + 1) * label_length]
+                .iter()
+                .map(|v| *v as char)
+                .collect::<String>()
+                .trim_end_matches(&[' ', '\0'][..])
+                .to_string();
+
+            Event {
+                label,
+                time: times[i],
+                frame: Event::frame_at(times[i], frame_rate),
+                // 0 means on, 1 means off.
+                display: display_flags[i] == 0,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// Events of a trial from both the header and the EVENT group, ordered by time.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EventSet {
+    pub events: Vec<Event>,
+}
+
+impl EventSet {
+    /// merge header events with the EVENT group, events present in both are kept once.
+    pub fn from_parameter(header: &HeaderBlock, parameter: &ParameterBlock) -> Self {
+        let frame_rate = parameter
+            .get("POINT:RATE")
+            .and_then(|v| v.get_scalar::<f32>())
+            .unwrap_or(header.frame_rate);
+
+        let mut events = from_group(parameter, frame_rate);
+        for event in from_header(header) {
+            // header labels are cut to 4 characters, an empty one matches nothing but itself.
+            let duplicated = events.iter().any(|v| {
+                v.frame == event.frame
+                    && (v.label == event.label
+                        || !event.label.is_empty() && v.label.starts_with(&event.label))
+            });
+            if !duplicated {
+                events.push(event);
+            }
+        }
+
+        let mut set = EventSet { events };
+        set.sort();
+        set
+    }
+
+    pub fn sort(&mut self) {
+        self.events.sort_by(|a, b| {
+            a.time
+                .partial_cmp(&b.time)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.context.cmp(&b.context))
+        });
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Event> {
+        self.events.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// events of the given context and label, e.g. ("Left", "Foot Strike").
+    pub fn filter<'a>(
+        &'a self,
+        context: &'a str,
+        label: &'a str,
+    ) -> impl Iterator<Item = &'a Event> + 'a {
+        self.events
+            .iter()
+            .filter(move |v| v.context == context && v.label == label)
+    }
+
+    /// replace the EVENT group of `parameter` with these events, ready to be passed to the
+    /// writer. Dimensions are single bytes, so at most 255 events of up to 255 characters
+    /// each can be stored.
+    pub fn write_to(&self, parameter: &mut ParameterBlock) -> Result<(), ParserError> {
+        let counts = self.events.len();
+        if counts > u8::MAX as usize {
+            return Err(ParserError::TooManyEvents(counts));
+        }
+
+        let strings = |f: fn(&Event) -> &str| self.events.iter().map(f).collect::<Vec<_>>();
+        let fields = [
+            ("context", strings(|v| &v.context)),
+            ("label", strings(|v| &v.label)),
+            ("description", strings(|v| &v.description)),
+            ("subject", strings(|v| &v.subject)),
+        ];
+        for (field, values) in fields.iter() {
+            let length = values.iter().map(|v| v.chars().count()).max();
+            if let Some(length) = length.filter(|v| *v > u8::MAX as usize) {
+                return Err(ParserError::EventStringTooLong { field, length });
+            }
+        }
+
+        let times = self
+            .events
+            .iter()
+            .flat_map(|v| {
+                let minutes = (v.time / 60.0).floor();
+                vec![minutes, v.time - minutes * 60.0]
+            })
+            .collect::<Vec<f32>>();
+
+        // parameters of the old group left behind would describe other events.
+        parameter.remove_group("EVENT");
+        parameter.insert("EVENT", ParameterFormat::scalar("USED", counts as i16))?;
+        parameter.insert(
+            "EVENT",
+            ParameterFormat::strings("CONTEXTS", &strings(|v| &v.context)),
+        )?;
+        parameter.insert(
+            "EVENT",
+            ParameterFormat::strings("LABELS", &strings(|v| &v.label)),
+        )?;
+        parameter.insert(
+            "EVENT",
+            ParameterFormat::strings("DESCRIPTIONS", &strings(|v| &v.description)),
+        )?;
+        parameter.insert(
+            "EVENT",
+            ParameterFormat::strings("SUBJECTS", &strings(|v| &v.subject)),
+        )?;
+        parameter.insert(
+            "EVENT",
+            ParameterFormat::new("TIMES", vec![2, counts as u8], ParamData::Float32(times)),
+        )?;
+        parameter.insert(
+            "EVENT",
+            ParameterFormat::new(
+                "ICON_IDS",
+                vec![counts as u8],
+                ParamData::Int16(self.events.iter().map(|v| v.icon_id).collect()),
+            ),
+        )?;
+        parameter.insert(
+            "EVENT",
+            ParameterFormat::new(
+                "GENERIC_FLAGS",
+                vec![counts as u8],
+                ParamData::Int16(self.events.iter().map(|v| v.generic_flag).collect()),
+            ),
+        )?;
+
+        Ok(())
+    }
+}
+
+impl<'a> IntoIterator for &'a EventSet {
+    type Item = &'a Event;
+    type IntoIter = std::slice::Iter<'a, Event>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.events.iter()
+    }
+}
+
+/// events of the EVENT group, TIMES holds (minutes, seconds) pairs.
+fn from_group(parameter: &ParameterBlock, frame_rate: f32) -> Vec<Event> {
+    let times = match parameter.get("EVENT:TIMES").and_then(|v| v.as_f32_vec()) {
+        Some(times) => times,
+        None => return vec![],
+    };
+    let counts = parameter
+        .get("EVENT:USED")
+        .and_then(|v| v.get_scalar::<i16>())
+        .map(|v| v.max(0) as usize)
+        .unwrap_or(times.len() / 2)
+        .min(times.len() / 2);
+
+    let strings = |key: &str| {
+        parameter
+            .get(key)
+            .and_then(|v| v.as_strings())
+            .unwrap_or_default()
+    };
+    let integers = |key: &str| {
+        parameter
+            .get(key)
+            .and_then(|v| v.as_i16_vec())
+            .unwrap_or_default()
+    };
+
+    let contexts = strings("EVENT:CONTEXTS");
+    let labels = strings("EVENT:LABELS");
+    let descriptions = strings("EVENT:DESCRIPTIONS");
+    let subjects = strings("EVENT:SUBJECTS");
+    let icon_ids = integers("EVENT:ICON_IDS");
+    let generic_flags = integers("EVENT:GENERIC_FLAGS");
+
+    (0..counts)
+        .map(|i| {
+            let time = times[2 * i] * 60.0 + times[2 * i + 1];
+            Event {
+                label: labels.get(i).cloned().unwrap_or_default(),
+                time,
+                frame: Event::frame_at(time, frame_rate),
+                display: true,
+                context: contexts.get(i).cloned().unwrap_or_default(),
+                description: descriptions.get(i).cloned().unwrap_or_default(),
+                subject: subjects.get(i).cloned().unwrap_or_default(),
+                icon_id: icon_ids.get(i).copied().unwrap_or_default(),
+                generic_flag: generic_flags.get(i).copied().unwrap_or_default(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_events() {
+        let mut header = HeaderBlock {
+            frame_rate: 100.0,
+            ..Default::default()
+        };
+        header.event_lables_long = LONG_LABELS_KEY;
+        header.event_counts = 2;
+
+        let mut times = [0.0; 18];
+        times[0] = 0.5;
+        times[1] = 1.234;
+        header.event_times = times;
+
+        let mut flags = [0; 18];
+        flags[1] = 1;
+        header.event_display_flags = flags;
+
+        let mut labels = [b' '; 72];
+        labels[..4].copy_from_slice(b"RHS ");
+        labels[4..8].copy_from_slice(b"LTO ");
+        header.event_labels = labels;
+
+        let events = from_header(&header);
+        assert_eq!(
+            events,
+            vec![
+                Event {
+                    label: "RHS".to_string(),
+                    time: 0.5,
+                    frame: 51,
+                    display: true,
+                    ..Default::default()
+                },
+                Event {
+                    label: "LTO".to_string(),
+                    time: 1.234,
+                    frame: 124,
+                    display: false,
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fixture_events() -> anyhow::Result<()> {
+        assert_eq!(std::mem::size_of::<HeaderBlock>(), 512);
+
+        let file = std::fs::File::open("test_data/vicon_trial.c3d")?;
+        let adapter = crate::C3dAdapter::new(file)?.construct()?;
+        let header = adapter.header.as_ref().unwrap();
+        assert_eq!({ header.event_lables_long }, LONG_LABELS_KEY);
+        assert_eq!(adapter.events(), Some(vec![]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_event_set() -> anyhow::Result<()> {
+        let mut header = HeaderBlock {
+            frame_rate: 100.0,
+            ..Default::default()
+        };
+        header.event_lables_long = LONG_LABELS_KEY;
+        header.event_counts = 2;
+        let mut times = [0.0; 18];
+        times[0] = 0.5;
+        times[1] = 0.1;
+        header.event_times = times;
+        let mut labels = [b' '; 72];
+        labels[..4].copy_from_slice(b"Foot");
+        labels[4..8].copy_from_slice(b"Star");
+        header.event_labels = labels;
+
+        let mut parameter = ParameterBlock::new();
+        parameter.insert("EVENT", ParameterFormat::scalar("USED", 2_i16))?;
+        parameter.insert(
+            "EVENT",
+            ParameterFormat::strings("CONTEXTS", &["Right", "Left"]),
+        )?;
+        parameter.insert(
+            "EVENT",
+            ParameterFormat::strings("LABELS", &["Foot Off", "Foot Strike"]),
+        )?;
+        parameter.insert(
+            "EVENT",
+            ParameterFormat::new(
+                "TIMES",
+                vec![2, 2],
+                ParamData::Float32(vec![1.0, 2.25, 0.0, 0.5]),
+            ),
+        )?;
+        parameter.insert(
+            "EVENT",
+            ParameterFormat::new("ICON_IDS", vec![2], ParamData::Int16(vec![2, 1])),
+        )?;
+
+        let events = EventSet::from_parameter(&header, &parameter);
+        let summary = events
+            .iter()
+            .map(|v| (v.context.as_str(), v.label.as_str(), v.frame, v.icon_id))
+            .collect::<Vec<_>>();
+        // the header "Foot" at 0.5s duplicates the left foot strike.
+        assert_eq!(
+            summary,
+            vec![
+                ("", "Star", 11, 0),
+                ("Left", "Foot Strike", 51, 1),
+                ("Right", "Foot Off", 6226, 2),
+            ]
+        );
+        assert_eq!(events.filter("Left", "Foot Strike").count(), 1);
+
+        // a header event without a label is no duplicate of a labelled one.
+        let mut unlabelled = header;
+        unlabelled.event_labels = [b' '; 72];
+        let merged = EventSet::from_parameter(&unlabelled, &parameter);
+        assert_eq!(merged.filter("", "").count(), 2);
+        assert_eq!(merged.len(), 4);
+
+        // the group is replaced as a whole.
+        let mut parameter = ParameterBlock::new();
+        parameter.insert("Event", ParameterFormat::scalar("STALE", 1_i16))?;
+        events.write_to(&mut parameter)?;
+        assert!(parameter.get("EVENT:STALE").is_none());
+        assert_eq!(parameter.groups.len(), 1);
+
+        let adapter = crate::tests::synthetic(HeaderBlock::default(), parameter, vec![])?;
+        let header = HeaderBlock {
+            frame_rate: 100.0,
+            ..Default::default()
+        };
+        let read = EventSet::from_parameter(&header, adapter.parameter.as_ref().unwrap());
+        assert_eq!(read, events);
+
+        // longer strings would wrap the single byte dimension.
+        let mut events = events;
+        events.events[1].description = "x".repeat(300);
+        let mut parameter = ParameterBlock::new();
+        assert!(matches!(
+            events.write_to(&mut parameter),
+            Err(ParserError::EventStringTooLong {
+                field: "description",
+                length: 300
+            })
+        ));
+        assert!(parameter.groups.is_empty());
+
+        let long = "y".repeat(300);
+        let strings = ParameterFormat::strings("LABELS", &[&long, "LASI"]);
+        assert_eq!(strings.dimensions, vec![255, 2]);
+        assert_eq!(strings.as_strings().unwrap()[0], long[..255]);
+
+        Ok(())
+    }
+}
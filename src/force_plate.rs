@@ -0,0 +1,817 @@
+use crate::{AnalogData, ParameterBlock, ParserError};
+use std::ops::RangeInclusive;
+
+pub type Vector3 = [f32; 3];
+
+/// Layout of the analog channels of a plate, from FORCE_PLATFORM:TYPE.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlateType {
+    /// Fx, Fy, Fz, Px, Py, Tz with the center of pressure computed by the amplifier.
+    Type1,
+    /// Fx, Fy, Fz, Mx, My, Mz about the transducer origin (AMTI, Bertec).
+    Type2,
+    /// Kistler fx12, fx34, fy14, fy23, fz1, fz2, fz3, fz4 with sensors at (±a, ±b).
+    Type3,
+    /// as type 2, but the channels need CAL_MATRIX to become forces and moments.
+    Type4,
+    /// 8 raw channels mapped by a 6x8 CAL_MATRIX to the channels of type 2.
+    Type5,
+    /// Kistler fx, fy, fz of each of the 4 sensors, mapped by a 12x12 CAL_MATRIX.
+    Type6,
+    /// 8 raw channels mapped by an 8x8 CAL_MATRIX to the channels of type 3.
+    Type7,
+}
+
+impl PlateType {
+    pub fn from_code(code: i16) -> Option<Self> {
+        match code {
+            1 => Some(PlateType::Type1),
+            2 => Some(PlateType::Type2),
+            3 => Some(PlateType::Type3),
+            4 => Some(PlateType::Type4),
+            5 => Some(PlateType::Type5),
+            6 => Some(PlateType::Type6),
+            7 => Some(PlateType::Type7),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> i16 {
+        match self {
+            PlateType::Type1 => 1,
+            PlateType::Type2 => 2,
+            PlateType::Type3 => 3,
+            PlateType::Type4 => 4,
+            PlateType::Type5 => 5,
+            PlateType::Type6 => 6,
+            PlateType::Type7 => 7,
+        }
+    }
+
+    /// number of analog channels the plate occupies.
+    pub fn channel_counts(self) -> usize {
+        match self {
+            PlateType::Type3 | PlateType::Type5 | PlateType::Type7 => 8,
+            PlateType::Type6 => 12,
+            _ => 6,
+        }
+    }
+
+    /// rows of CAL_MATRIX, None if the type is not calibrated.
+    fn calibration_rows(self) -> Option<usize> {
+        match self {
+            PlateType::Type4 | PlateType::Type5 => Some(6),
+            PlateType::Type6 => Some(12),
+            PlateType::Type7 => Some(8),
+            _ => None,
+        }
+    }
+}
+
+/// Forces and moments of one plate at one analog sample, expressed in the lab frame.
+///
+/// The force is the one exerted on the plate, as measured by the transducer, the ground
+/// reaction force acting on the subject is its negation.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Wrench {
+    pub force: Vector3,
+    /// moment about the center of the plate surface.
+    pub moment: Vector3,
+    /// None while the vertical force is below the plate threshold.
+    pub cop: Option<Vector3>,
+    /// torque about the plate normal at the center of pressure.
+    pub free_moment: Vector3,
+}
+
+/// A force plate described by the FORCE_PLATFORM group.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForcePlatform {
+    pub plate_type: PlateType,
+    /// corners in the lab frame, at (+x, +y), (-x, +y), (-x, -y) and (+x, -y) of the plate.
+    pub corners: [Vector3; 4],
+    /// transducer origin relative to the center of the plate surface, in the plate frame. For
+    /// type 3 plates this holds the sensor offsets (a, b, az0) instead.
+    pub origin: Vector3,
+    /// 0 based analog channels, in the order of the plate type.
+    pub channels: Vec<usize>,
+    /// rows of the calibration matrix, applied to the channels of calibrated plate types.
+    pub calibration: Option<Vec<Vec<f32>>>,
+    /// vertical force below which no center of pressure is reported, 10 N by default.
+    pub threshold: f32,
+}
+
+impl ForcePlatform {
+    /// every plate listed in FORCE_PLATFORM:USED.
+    pub fn from_parameter(parameter: &ParameterBlock) -> Result<Vec<Self>, ParserError> {
+        let used = parameter
+            .get("FORCE_PLATFORM:USED")
+            .and_then(|v| v.get_scalar::<i16>())
+            .unwrap_or(0)
+            .max(0) as usize;
+
+        let types = parameter
+            .get("FORCE_PLATFORM:TYPE")
+            .and_then(|v| v.as_i16_vec())
+            .unwrap_or_default();
+        let corners = parameter
+            .get("FORCE_PLATFORM:CORNERS")
+            .and_then(|v| v.as_f32_vec())
+            .unwrap_or_default();
+        let origins = parameter
+            .get("FORCE_PLATFORM:ORIGIN")
+            .and_then(|v| v.as_f32_vec())
+            .unwrap_or_default();
+        let channel = parameter.get("FORCE_PLATFORM:CHANNEL");
+        let calibration = parameter.get("FORCE_PLATFORM:CAL_MATRIX");
+
+        (0..used)
+            .map(|index| {
+                let invalid = |reason| ParserError::InvalidForcePlatform { index, reason };
+
+                let plate_type = types
+                    .get(index)
+                    .copied()
+                    .ok_or_else(|| invalid("missing type"))?;
+                let plate_type =
+                    PlateType::from_code(plate_type).ok_or_else(|| invalid("unsupported type"))?;
+
+                let corners = corners
+                    .get(index * 12..(index + 1) * 12)
+                    .ok_or_else(|| invalid("missing corners"))?;
+                let corners = [
+                    [corners[0], corners[1], corners[2]],
+                    [corners[3], corners[4], corners[5]],
+                    [corners[6], corners[7], corners[8]],
+                    [corners[9], corners[10], corners[11]],
+                ];
+
+                let origin = origins
+                    .get(index * 3..(index + 1) * 3)
+                    .map(|v| [v[0], v[1], v[2]])
+                    .unwrap_or_default();
+
+                // CHANNEL is (channels per plate, plates) and 1 based.
+                let channel = channel.ok_or_else(|| invalid("missing channels"))?;
+                let rows = channel.dimensions.first().copied().unwrap_or(0) as usize;
+                let channels = channel
+                    .values::<i16>()
+                    .and_then(|v| v.get(index * rows..(index + 1) * rows))
+                    .filter(|v| v.len() >= plate_type.channel_counts())
+                    .ok_or_else(|| invalid("missing channels"))?[..plate_type.channel_counts()]
+                    .iter()
+                    .map(|v| (*v as usize).checked_sub(1))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| invalid("channels are 1 based"))?;
+
+                let calibration = if let Some(outputs) = plate_type.calibration_rows() {
+                    let matrix = calibration
+                        .and_then(|v| {
+                            let rows = *v.dimensions.first()? as usize;
+                            let columns = *v.dimensions.get(1)? as usize;
+                            let data = v
+                                .values::<f32>()?
+                                .get(index * rows * columns..(index + 1) * rows * columns)?;
+                            // the first dimension varies fastest.
+                            Some(
+                                (0..rows)
+                                    .map(|row| {
+                                        (0..columns).map(|col| data[row + col * rows]).collect()
+                                    })
+                                    .collect::<Vec<Vec<f32>>>(),
+                            )
+                        })
+                        .filter(|v| {
+                            v.len() == outputs && v.iter().all(|row| row.len() == channels.len())
+                        })
+                        .ok_or_else(|| invalid("missing calibration matrix"))?;
+                    Some(matrix)
+                } else {
+                    None
+                };
+
+                Ok(ForcePlatform {
+                    plate_type,
+                    corners,
+                    origin,
+                    channels,
+                    calibration,
+                    threshold: 10.0,
+                })
+            })
+            .collect()
+    }
+
+    /// center of the plate surface in the lab frame.
+    pub fn center(&self) -> Vector3 {
+        let mut center = [0.0; 3];
+        for corner in self.corners.iter() {
+            for (c, v) in center.iter_mut().zip(corner.iter()) {
+                *c += v / 4.0;
+            }
+        }
+        center
+    }
+
+    /// unit axes of the plate frame in the lab frame, z = x × y usually points into the floor.
+    pub fn axes(&self) -> [Vector3; 3] {
+        let x = normalize(sub(self.corners[0], self.corners[1]));
+        let y = normalize(sub(self.corners[0], self.corners[3]));
+        let z = normalize(cross(x, y));
+        [x, y, z]
+    }
+
+    /// wrench at every subframe of the analog frame.
+    pub fn wrenches(&self, analog: &AnalogData) -> Vec<Wrench> {
+        (0..analog.subframes)
+            .filter_map(|subframe| self.wrench(analog, subframe))
+            .collect()
+    }
+
+    /// None if the analog frame lacks the channels of the plate.
+    pub fn wrench(&self, analog: &AnalogData, subframe: usize) -> Option<Wrench> {
+        let raw = self
+            .channels
+            .iter()
+            .map(|c| analog.get(*c, subframe))
+            .collect::<Option<Vec<f32>>>()?;
+
+        let raw = match self.calibration {
+            Some(ref matrix) => matrix
+                .iter()
+                .map(|row| row.iter().zip(raw.iter()).map(|(m, v)| m * v).sum())
+                .collect(),
+            None => raw,
+        };
+
+        let (force, moment) = self.plate_wrench(&raw);
+        Some(self.to_lab(force, moment))
+    }
+
+    /// force and moment about the center of the plate surface, in the plate frame.
+    fn plate_wrench(&self, raw: &[f32]) -> (Vector3, Vector3) {
+        match self.plate_type {
+            PlateType::Type1 => {
+                let force = [raw[0], raw[1], raw[2]];
+                let cop = [raw[3], raw[4], 0.0];
+                let moment = cross(cop, force);
+                (force, [moment[0], moment[1], moment[2] + raw[5]])
+            }
+            PlateType::Type2 | PlateType::Type4 | PlateType::Type5 => {
+                let force = [raw[0], raw[1], raw[2]];
+                let moment = [raw[3], raw[4], raw[5]];
+                (force, transfer(moment, self.origin, force))
+            }
+            PlateType::Type3 | PlateType::Type7 => {
+                let [a, b, az0] = self.origin;
+                let (fx12, fx34, fy14, fy23) = (raw[0], raw[1], raw[2], raw[3]);
+                let (fz1, fz2, fz3, fz4) = (raw[4], raw[5], raw[6], raw[7]);
+
+                let force = [fx12 + fx34, fy14 + fy23, fz1 + fz2 + fz3 + fz4];
+                let moment = [
+                    b * (fz1 + fz2 - fz3 - fz4),
+                    a * (-fz1 + fz2 + fz3 - fz4),
+                    b * (-fx12 + fx34) + a * (fy14 - fy23),
+                ];
+                // az0 is the (negative) height of the surface above the sensor plane.
+                (force, transfer(moment, [0.0, 0.0, -az0], force))
+            }
+            PlateType::Type6 => {
+                let [a, b, az0] = self.origin;
+                // same sensor placement as type 3.
+                let sensors = [[a, b, 0.0], [-a, b, 0.0], [-a, -b, 0.0], [a, -b, 0.0]];
+
+                let mut force = [0.0; 3];
+                let mut moment = [0.0; 3];
+                for (position, f) in sensors.iter().zip(raw.chunks_exact(3)) {
+                    let f = [f[0], f[1], f[2]];
+                    let arm = cross(*position, f);
+                    for i in 0..3 {
+                        force[i] += f[i];
+                        moment[i] += arm[i];
+                    }
+                }
+                (force, transfer(moment, [0.0, 0.0, -az0], force))
+            }
+        }
+    }
+
+    fn to_lab(&self, force: Vector3, moment: Vector3) -> Wrench {
+        let axes = self.axes();
+        let rotate = |v: Vector3| {
+            let mut rv = [0.0; 3];
+            for (axis, value) in axes.iter().zip(v.iter()) {
+                for (r, a) in rv.iter_mut().zip(axis.iter()) {
+                    *r += a * value;
+                }
+            }
+            rv
+        };
+
+        // a force at (x, y, 0) with a torque tz about z gives the moment about the center.
+        let (cop, free_moment) = if force[2].abs() < self.threshold {
+            (None, 0.0)
+        } else {
+            let x = -moment[1] / force[2];
+            let y = moment[0] / force[2];
+            let tz = moment[2] - x * force[1] + y * force[0];
+            let center = self.center();
+            let cop = rotate([x, y, 0.0]);
+            (
+                Some([center[0] + cop[0], center[1] + cop[1], center[2] + cop[2]]),
+                tz,
+            )
+        };
+
+        Wrench {
+            force: rotate(force),
+            moment: rotate(moment),
+            cop,
+            free_moment: rotate([0.0, 0.0, free_moment]),
+        }
+    }
+}
+
+/// Per channel means of the plate channels over unloaded frames, subtracted from every
+/// analog sample to remove the drift of the amplifiers.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Baseline {
+    /// (0 based analog channel, mean) sorted by channel.
+    pub offsets: Vec<(usize, f32)>,
+}
+
+impl Baseline {
+    /// frames listed in FORCE_PLATFORM:ZERO, None if zeroing is disabled (0 or an empty range).
+    pub fn zero_range(parameter: &ParameterBlock) -> Option<RangeInclusive<u32>> {
+        let zero = parameter
+            .get("FORCE_PLATFORM:ZERO")
+            .and_then(|v| v.as_i16_vec())
+            .filter(|v| v.len() >= 2)?;
+        let (first, last) = (zero[0] as u16 as u32, zero[1] as u16 as u32);
+
+        if first == 0 || last < first {
+            return None;
+        }
+        Some(first..=last)
+    }
+
+    /// average every plate channel over the analog frames.
+    pub fn compute<'a>(
+        plates: &[ForcePlatform],
+        frames: impl IntoIterator<Item = &'a AnalogData>,
+    ) -> Self {
+        let mut channels = plates
+            .iter()
+            .flat_map(|plate| {
+                plate
+                    .channels
+                    .iter()
+                    .enumerate()
+                    // the center of pressure of type 1 plates does not drift.
+                    .filter(move |(i, _)| {
+                        plate.plate_type != PlateType::Type1 || (*i != 3 && *i != 4)
+                    })
+                    .map(|(_, c)| *c)
+            })
+            .collect::<Vec<_>>();
+        channels.sort_unstable();
+        channels.dedup();
+
+        let mut sums = vec![0_f64; channels.len()];
+        let mut counts = vec![0_usize; channels.len()];
+        for analog in frames {
+            for (i, channel) in channels.iter().enumerate() {
+                if let Some(values) = analog.channel(*channel) {
+                    sums[i] += values.iter().map(|v| *v as f64).sum::<f64>();
+                    counts[i] += values.len();
+                }
+            }
+        }
+
+        let offsets = channels
+            .into_iter()
+            .zip(sums.into_iter().zip(counts))
+            .filter(|(_, (_, count))| *count > 0)
+            .map(|(channel, (sum, count))| (channel, (sum / count as f64) as f32))
+            .collect();
+
+        Baseline { offsets }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// the mean subtracted from the channel, 0 if it is not a plate channel.
+    pub fn offset(&self, channel: usize) -> f32 {
+        self.offsets
+            .binary_search_by_key(&channel, |(c, _)| *c)
+            .map(|idx| self.offsets[idx].1)
+            .unwrap_or(0.0)
+    }
+
+    /// subtract the baseline from every subframe of the channels.
+    pub fn apply(&self, analog: &mut AnalogData) {
+        let subframes = analog.subframes;
+        for (channel, offset) in self.offsets.iter() {
+            if *channel >= analog.channels {
+                continue;
+            }
+            analog.values[channel * subframes..(channel + 1) * subframes]
+                .iter_mut()
+                .for_each(|v| *v -= offset);
+        }
+    }
+}
+
+/// moment about the plate center, given the moment about a point at `origin` from it.
+fn transfer(moment: Vector3, origin: Vector3, force: Vector3) -> Vector3 {
+    let arm = cross(origin, force);
+    [moment[0] + arm[0], moment[1] + arm[1], moment[2] + arm[2]]
+}
+
+fn sub(a: Vector3, b: Vector3) -> Vector3 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: Vector3, b: Vector3) -> Vector3 {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(v: Vector3) -> Vector3 {
+    let norm = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if norm == 0.0 {
+        return v;
+    }
+    [v[0] / norm, v[1] / norm, v[2] / norm]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ParamData, ParameterFormat};
+
+    // a 500 mm plate with its z axis pointing into the floor, as in the vicon trial.
+    const CORNERS: [f32; 12] = [
+        0.0, 500.0, 0.0, 500.0, 500.0, 0.0, 500.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+    ];
+
+    // load in the plate frame: force, center of pressure and free torque.
+    const FORCE: Vector3 = [10.0, 20.0, 800.0];
+    const COP: Vector3 = [50.0, -30.0, 0.0];
+    const TZ: f32 = 5.0;
+
+    fn plate_parameter(
+        plate_type: i16,
+        channels: usize,
+        origin: Vector3,
+        calibration: Option<Vec<f32>>,
+    ) -> Result<ParameterBlock, ParserError> {
+        let mut parameter = ParameterBlock::new();
+        parameter.insert("FORCE_PLATFORM", ParameterFormat::scalar("USED", 1_i16))?;
+        parameter.insert(
+            "FORCE_PLATFORM",
+            ParameterFormat::new("TYPE", vec![1], ParamData::Int16(vec![plate_type])),
+        )?;
+        parameter.insert(
+            "FORCE_PLATFORM",
+            ParameterFormat::new(
+                "CORNERS",
+                vec![3, 4, 1],
+                ParamData::Float32(CORNERS.to_vec()),
+            ),
+        )?;
+        parameter.insert(
+            "FORCE_PLATFORM",
+            ParameterFormat::new("ORIGIN", vec![3, 1], ParamData::Float32(origin.to_vec())),
+        )?;
+        parameter.insert(
+            "FORCE_PLATFORM",
+            ParameterFormat::new(
+                "CHANNEL",
+                vec![channels as u8, 1],
+                ParamData::Int16((1..=channels as i16).collect()),
+            ),
+        )?;
+        if let Some(calibration) = calibration {
+            parameter.insert(
+                "FORCE_PLATFORM",
+                ParameterFormat::new(
+                    "CAL_MATRIX",
+                    vec![(calibration.len() / channels) as u8, channels as u8, 1],
+                    ParamData::Float32(calibration),
+                ),
+            )?;
+        }
+        Ok(parameter)
+    }
+
+    fn single_sample(values: &[f32]) -> AnalogData {
+        AnalogData {
+            channels: values.len(),
+            subframes: 1,
+            values: values.to_vec(),
+        }
+    }
+
+    // moment of the known load about the plate center.
+    fn center_moment() -> Vector3 {
+        let moment = cross(COP, FORCE);
+        [moment[0], moment[1], moment[2] + TZ]
+    }
+
+    fn assert_known_load(plate: &ForcePlatform, analog: &AnalogData) {
+        let wrench = plate.wrench(analog, 0).unwrap();
+        let close =
+            |a: Vector3, b: Vector3| a.iter().zip(b.iter()).all(|(a, b)| (a - b).abs() < 1e-2);
+
+        // plate x and z are flipped against the lab axes.
+        assert!(close(wrench.force, [-10.0, 20.0, -800.0]), "{:?}", wrench);
+        assert!(
+            close(wrench.cop.unwrap(), [200.0, 220.0, 0.0]),
+            "{:?}",
+            wrench
+        );
+        assert!(close(wrench.free_moment, [0.0, 0.0, -TZ]), "{:?}", wrench);
+        let moment = center_moment();
+        assert!(
+            close(wrench.moment, [-moment[0], moment[1], -moment[2]]),
+            "{:?}",
+            wrench
+        );
+    }
+
+    // channels of a type 2 plate with its transducer at `origin`.
+    fn type2_channels(origin: Vector3) -> Vec<f32> {
+        let moment = transfer(center_moment(), origin, [-FORCE[0], -FORCE[1], -FORCE[2]]);
+        vec![
+            FORCE[0], FORCE[1], FORCE[2], moment[0], moment[1], moment[2],
+        ]
+    }
+
+    #[test]
+    fn test_basic_types() -> anyhow::Result<()> {
+        let parameter = plate_parameter(1, 6, [0.0; 3], None)?;
+        let plate = &ForcePlatform::from_parameter(&parameter)?[0];
+        assert_eq!(plate.plate_type, PlateType::Type1);
+        assert_known_load(
+            plate,
+            &single_sample(&[FORCE[0], FORCE[1], FORCE[2], COP[0], COP[1], TZ]),
+        );
+
+        let origin = [3.0, -2.0, 40.0];
+        let parameter = plate_parameter(2, 6, origin, None)?;
+        let plate = &ForcePlatform::from_parameter(&parameter)?[0];
+        assert_known_load(plate, &single_sample(&type2_channels(origin)));
+
+        // a scaled identity with fz picking up crosstalk from fx, stored column by column.
+        let channels = type2_channels(origin);
+        let mut calibration = vec![0.0; 36];
+        for i in 0..6 {
+            calibration[i + i * 6] = 2.0;
+        }
+        calibration[2] = 0.5;
+        let mut raw = channels.iter().map(|v| v / 2.0).collect::<Vec<_>>();
+        raw[2] = (channels[2] - 0.5 * raw[0]) / 2.0;
+
+        let parameter = plate_parameter(4, 6, origin, Some(calibration))?;
+        let plate = &ForcePlatform::from_parameter(&parameter)?[0];
+        assert_known_load(plate, &single_sample(&raw));
+
+        let parameter = plate_parameter(4, 6, origin, None)?;
+        assert!(matches!(
+            ForcePlatform::from_parameter(&parameter),
+            Err(ParserError::InvalidForcePlatform { index: 0, .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hand_computed_channels() -> anyhow::Result<()> {
+        // transducer 40 mm under the surface, off center by (3, -2). The load acts at
+        // COP - ORIGIN = (47, -28, -40) from it, so (47, -28, -40) x (10, 20, 800) gives
+        // Mx = -28 * 800 + 40 * 20 = -21600, My = -40 * 10 - 47 * 800 = -38000 and
+        // Mz = 47 * 20 + 28 * 10 + TZ = 1225.
+        let origin = [3.0, -2.0, 40.0];
+        let channels = [10.0, 20.0, 800.0, -21600.0, -38000.0, 1225.0];
+        let parameter = plate_parameter(2, 6, origin, None)?;
+        let plate = &ForcePlatform::from_parameter(&parameter)?[0];
+        assert_known_load(plate, &single_sample(&channels));
+
+        // identity but for fx picking up half of fy, row 0 and column 1 is the 7th value
+        // when stored column by column.
+        let mut calibration = vec![0.0; 36];
+        for i in [0, 7, 14, 21, 28, 35] {
+            calibration[i] = 1.0;
+        }
+        calibration[6] = 0.5;
+        let raw = [0.0, 20.0, 800.0, -21600.0, -38000.0, 1225.0];
+        let parameter = plate_parameter(4, 6, origin, Some(calibration))?;
+        let plate = &ForcePlatform::from_parameter(&parameter)?[0];
+        assert_eq!(plate.calibration.as_ref().unwrap()[0][1], 0.5);
+        assert_known_load(plate, &single_sample(&raw));
+
+        Ok(())
+    }
+
+    // channels of a type 3 plate with sensors at (±a, ±b) and the surface az0 above them.
+    fn kistler_channels(a: f32, b: f32, az0: f32) -> Vec<f32> {
+        let moment = transfer(
+            center_moment(),
+            [0.0, 0.0, -az0],
+            [-FORCE[0], -FORCE[1], -FORCE[2]],
+        );
+
+        let (s, p, q) = (FORCE[2], moment[0] / b, moment[1] / a);
+        let fy = moment[2] / a;
+        vec![
+            FORCE[0] / 2.0,
+            FORCE[0] / 2.0,
+            (FORCE[1] + fy) / 2.0,
+            (FORCE[1] - fy) / 2.0,
+            (s + p - q) / 4.0,
+            (s + p + q) / 4.0,
+            (s - p + q) / 4.0,
+            (s - p - q) / 4.0,
+        ]
+    }
+
+    // a rows x columns matrix stored column by column.
+    fn column_major(rows: usize, columns: usize, at: impl Fn(usize, usize) -> f32) -> Vec<f32> {
+        (0..columns)
+            .flat_map(|col| (0..rows).map(move |row| (row, col)))
+            .map(|(row, col)| at(row, col))
+            .collect()
+    }
+
+    #[test]
+    fn test_kistler() -> anyhow::Result<()> {
+        let origin = [120.0, 200.0, -45.0];
+        let raw = kistler_channels(origin[0], origin[1], origin[2]);
+
+        let parameter = plate_parameter(3, 8, origin, None)?;
+        let plate = &ForcePlatform::from_parameter(&parameter)?[0];
+        assert_eq!(plate.channels, (0..8).collect::<Vec<_>>());
+        assert_known_load(plate, &single_sample(&raw));
+
+        // type 7 runs the same channels through an 8x8 matrix first.
+        let calibration = column_major(8, 8, |row, col| if row == col { 2.0 } else { 0.0 });
+        let halved = raw.iter().map(|v| v / 2.0).collect::<Vec<_>>();
+        let parameter = plate_parameter(7, 8, origin, Some(calibration))?;
+        let plate = &ForcePlatform::from_parameter(&parameter)?[0];
+        assert_eq!(plate.plate_type, PlateType::Type7);
+        assert_known_load(plate, &single_sample(&halved));
+
+        // type 6 has every sensor on its own channels, fx and fy are split evenly.
+        let [fx12, fx34, fy14, fy23, fz1, fz2, fz3, fz4] = [
+            raw[0], raw[1], raw[2], raw[3], raw[4], raw[5], raw[6], raw[7],
+        ];
+        let sensors = [
+            [fx12 / 2.0, fy14 / 2.0, fz1],
+            [fx12 / 2.0, fy23 / 2.0, fz2],
+            [fx34 / 2.0, fy23 / 2.0, fz3],
+            [fx34 / 2.0, fy14 / 2.0, fz4],
+        ]
+        .concat();
+        // swap the x and y channel of every sensor in the calibration.
+        let swapped = |i: usize| match i % 3 {
+            0 => i + 1,
+            1 => i - 1,
+            _ => i,
+        };
+        let calibration = column_major(12, 12, |row, col| (swapped(row) == col) as u8 as f32 * 0.5);
+        let raw = (0..12)
+            .map(|i| sensors[swapped(i)] * 2.0)
+            .collect::<Vec<_>>();
+        let parameter = plate_parameter(6, 12, origin, Some(calibration))?;
+        let plate = &ForcePlatform::from_parameter(&parameter)?[0];
+        assert_eq!(plate.channels.len(), 12);
+        assert_known_load(plate, &single_sample(&raw));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_amti_eight_channels() -> anyhow::Result<()> {
+        let origin = [3.0, -2.0, 40.0];
+        let mut raw = type2_channels(origin);
+        raw.extend_from_slice(&[1.0, -1.0]);
+
+        // the two extra channels cancel out on fz.
+        let calibration = column_major(6, 8, |row, col| match (row, col) {
+            (2, 6) | (2, 7) => 3.0,
+            (row, col) if row == col => 1.0,
+            _ => 0.0,
+        });
+        let parameter = plate_parameter(5, 8, origin, Some(calibration.clone()))?;
+        let plate = &ForcePlatform::from_parameter(&parameter)?[0];
+        assert_eq!(plate.plate_type, PlateType::Type5);
+        assert_known_load(plate, &single_sample(&raw));
+
+        // a square matrix cannot map 8 channels to the 6 of type 2.
+        let square = column_major(8, 8, |row, col| {
+            calibration.get(row + col * 6).copied().unwrap_or(0.0)
+        });
+        let mut parameter = plate_parameter(5, 8, origin, None)?;
+        parameter.insert(
+            "FORCE_PLATFORM",
+            ParameterFormat::new("CAL_MATRIX", vec![8, 8, 1], ParamData::Float32(square)),
+        )?;
+        assert!(ForcePlatform::from_parameter(&parameter).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fixture_plate() -> anyhow::Result<()> {
+        let file = std::fs::File::open("test_data/vicon_trial.c3d")?;
+        let adapter = crate::C3dAdapter::new(file)?.construct()?;
+        let plates = adapter.force_platforms()?;
+        assert_eq!(plates.len(), 1);
+
+        let plate = &plates[0];
+        assert_eq!(plate.plate_type, PlateType::Type2);
+        assert_eq!(plate.channels, (0..6).collect::<Vec<_>>());
+        assert_eq!(plate.center(), [250.0, 250.0, 0.0]);
+
+        // nobody steps on the plate in this trial, only the sensor noise is left.
+        for (_, _, analog) in adapter.reader()? {
+            let wrenches = plate.wrenches(analog.as_ref().unwrap());
+            assert_eq!(wrenches.len(), 4);
+            assert!(wrenches.iter().all(|v| v.force[2].abs() < 100.0));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_baseline() -> anyhow::Result<()> {
+        use crate::{C3dAdapter, HeaderBlock, PointData};
+
+        let header = HeaderBlock {
+            analog_counts: 6,
+            frame_first: 1,
+            frame_last: 20,
+            scale: -1.0,
+            analog_per_frame: 1,
+            frame_rate: 100.0,
+            ..Default::default()
+        };
+        let origin = [3.0, -2.0, 40.0];
+        let mut parameter = plate_parameter(2, 6, origin, None)?;
+        parameter.insert("ANALOG", ParameterFormat::scalar("USED", 6_i16))?;
+        parameter.insert(
+            "FORCE_PLATFORM",
+            ParameterFormat::new("ZERO", vec![2], ParamData::Int16(vec![1, 10])),
+        )?;
+
+        // amplifier drift on every channel, the subject steps on at frame 11.
+        let drift = [5.0, -3.0, 20.0, 100.0, -50.0, 7.0];
+        let load = type2_channels(origin);
+        let frames = (1..=20).map(|idx| {
+            let values = drift
+                .iter()
+                .zip(load.iter())
+                .map(|(d, l)| if idx > 10 { d + l } else { *d })
+                .collect();
+            let analog = AnalogData {
+                channels: 6,
+                subframes: 1,
+                values,
+            };
+            (PointData { values: vec![] }, Some(analog))
+        });
+
+        let adapter = crate::tests::synthetic(header, parameter, frames)?;
+
+        let baseline = adapter.force_plate_baseline(None)?;
+        assert_eq!(
+            baseline.offsets,
+            drift.iter().copied().enumerate().collect::<Vec<_>>()
+        );
+
+        let plate = &adapter.force_platforms()?[0];
+        let (_, _, analog) = adapter.reader()?.with_baseline(baseline).nth(15).unwrap();
+        assert_known_load(plate, analog.as_ref().unwrap());
+
+        // an explicit range overrides ZERO.
+        let baseline = adapter.force_plate_baseline(Some(11..=20))?;
+        let (_, _, analog) = adapter.reader()?.with_baseline(baseline).nth(15).unwrap();
+        assert!(analog.unwrap().values.iter().all(|v| v.abs() < 1e-3));
+
+        // ZERO = (1, 0) disables zeroing in the vicon trial.
+        let file = std::fs::File::open("test_data/vicon_trial.c3d")?;
+        let adapter = C3dAdapter::new(file)?.construct()?;
+        assert!(adapter.force_plate_baseline(None)?.is_empty());
+        assert_eq!(
+            adapter.force_plate_baseline(Some(1..=100))?.offsets.len(),
+            6
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,254 @@
+use crate::{
+    AnalogData, AnalogFormat, AnalogScaling, Baseline, CameraMask, HeaderBlock, ParameterBlock,
+    Point, PointData, Processor,
+};
+use std::ops::Range;
+
+/// Markers and analog channels picked for a projected reader, in the requested order.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Projection {
+    pub(crate) markers: Vec<usize>,
+    pub(crate) channels: Vec<usize>,
+}
+
+/// Turns the bytes of one frame of the data section into points and analog samples, shared
+/// by every reader.
+#[derive(Debug, Clone)]
+pub(crate) struct FrameDecoder {
+    processor: Processor,
+    is_float: bool,
+    point_scale: f32,
+    residual_scale: f32,
+    point_counts: usize,
+    analog_counts: usize,
+    analog_per_frame: usize,
+    analog_scaling: AnalogScaling,
+    pub(crate) baseline: Option<Baseline>,
+}
+
+impl FrameDecoder {
+    pub(crate) fn new(header: &HeaderBlock, parameter: &ParameterBlock) -> Self {
+        let scale = header.scale;
+        let is_float = scale <= 0.0;
+
+        FrameDecoder {
+            processor: parameter.processor(),
+            is_float,
+            point_scale: if is_float { 1.0 } else { scale.abs() },
+            residual_scale: scale.abs(),
+            point_counts: header.point_counts as usize,
+            analog_counts: header.analog_counts as usize,
+            analog_per_frame: header.analog_per_frame as usize,
+            analog_scaling: AnalogScaling::from_parameter(parameter),
+            baseline: None,
+        }
+    }
+
+    /// bytes taken by a word, 4 in float files and 2 in integer files.
+    fn word_length(&self) -> usize {
+        if self.is_float {
+            4
+        } else {
+            2
+        }
+    }
+
+    /// bytes taken by one frame of points and analog samples.
+    pub(crate) fn frame_length(&self) -> usize {
+        (4 * self.point_counts + self.analog_counts) * self.word_length()
+    }
+
+    /// decode a frame of `frame_length` bytes.
+    pub(crate) fn decode(&self, bytes: &[u8]) -> (PointData, Option<AnalogData>) {
+        let word_length = self.word_length();
+        let (points, analog) = bytes.split_at(4 * self.point_counts * word_length);
+        (self.decode_points(points), self.decode_analog(analog))
+    }
+
+    fn decode_points(&self, bytes: &[u8]) -> PointData {
+        let values = bytes
+            .chunks_exact(4 * self.word_length())
+            .map(|arr| self.decode_point(arr))
+            .collect::<Vec<_>>();
+
+        PointData { values }
+    }
+
+    fn read_word(&self, bytes: &[u8]) -> f32 {
+        if self.is_float {
+            let mut buf = [0_u8; 4];
+            buf.copy_from_slice(bytes);
+            self.processor.read_f32(buf)
+        } else {
+            let mut buf = [0_u8; 2];
+            buf.copy_from_slice(bytes);
+            self.processor.read_i16(buf) as f32
+        }
+    }
+
+    /// decode the four words of a point.
+    pub(crate) fn decode_point(&self, bytes: &[u8]) -> Point {
+        let mut arr = [0_f32; 4];
+        bytes
+            .chunks_exact(self.word_length())
+            .zip(arr.iter_mut())
+            .for_each(|(bytes, v)| *v = self.read_word(bytes));
+        arr[..3].iter_mut().for_each(|v| *v *= self.point_scale);
+
+        // the fourth word holds the residual in its low byte and the cameras in its high
+        // byte, float files store the same word as a float.
+        if arr[3] < 0.0 {
+            return Point {
+                x: arr[0],
+                y: arr[1],
+                z: arr[2],
+                residual: None,
+                cameras: CameraMask::default(),
+            };
+        }
+
+        let word = arr[3] as i32;
+        Point {
+            x: arr[0],
+            y: arr[1],
+            z: arr[2],
+            residual: Some((word & 0xff) as f32 * self.residual_scale),
+            cameras: CameraMask(((word >> 8) & 0xff) as u8),
+        }
+    }
+
+    /// the point at `index` of a whole frame.
+    pub(crate) fn point(&self, frame: &[u8], index: usize) -> Option<Point> {
+        if index >= self.point_counts {
+            return None;
+        }
+        let length = 4 * self.word_length();
+        frame
+            .get(index * length..(index + 1) * length)
+            .map(|bytes| self.decode_point(bytes))
+    }
+
+    pub(crate) fn point_counts(&self) -> usize {
+        self.point_counts
+    }
+
+    /// (channels, subframes) of the analog samples in a frame.
+    pub(crate) fn analog_layout(&self) -> (usize, usize) {
+        if self.analog_counts == 0 {
+            return (0, 0);
+        }
+        self.analog_scaling
+            .layout(self.analog_counts, self.analog_per_frame)
+    }
+
+    fn read_sample(&self, bytes: &[u8]) -> f32 {
+        if !self.is_float && self.analog_scaling.format == AnalogFormat::Unsigned {
+            let mut buf = [0_u8; 2];
+            buf.copy_from_slice(bytes);
+            self.processor.read_u16(buf) as f32
+        } else {
+            self.read_word(bytes)
+        }
+    }
+
+    /// a single scaled analog sample of a whole frame.
+    pub(crate) fn sample(&self, frame: &[u8], channel: usize, subframe: usize) -> Option<f32> {
+        let (channels, subframes) = self.analog_layout();
+        if channel >= channels || subframe >= subframes {
+            return None;
+        }
+
+        let word_length = self.word_length();
+        let start = (4 * self.point_counts + subframe * channels + channel) * word_length;
+        let raw = self.read_sample(frame.get(start..start + word_length)?);
+
+        let mut value = self.analog_scaling.value(channel, raw);
+        if let Some(baseline) = self.baseline.as_ref() {
+            value -= baseline.offset(channel);
+        }
+        Some(value)
+    }
+
+    /// bytes of a frame covering every word the projection needs, the rest can be skipped.
+    pub(crate) fn span(&self, projection: &Projection) -> Range<usize> {
+        let word_length = self.word_length();
+        let (channels, subframes) = self.analog_layout();
+
+        let points = projection
+            .markers
+            .iter()
+            .map(|v| v * 4 * word_length..(v + 1) * 4 * word_length);
+        let analog = projection.channels.iter().map(|v| {
+            let first = 4 * self.point_counts + v;
+            let last = first + subframes.saturating_sub(1) * channels;
+            first * word_length..(last + 1) * word_length
+        });
+
+        points
+            .chain(analog)
+            .reduce(|a, b| a.start.min(b.start)..a.end.max(b.end))
+            .unwrap_or(0..0)
+    }
+
+    /// decode the projected markers and channels of a frame, other words are never read.
+    pub(crate) fn project(
+        &self,
+        frame: &[u8],
+        projection: &Projection,
+    ) -> (PointData, Option<AnalogData>) {
+        let values = projection
+            .markers
+            .iter()
+            .filter_map(|v| self.point(frame, *v))
+            .collect();
+
+        if projection.channels.is_empty() {
+            return (PointData { values }, None);
+        }
+
+        let (_, subframes) = self.analog_layout();
+        let analog = AnalogData {
+            channels: projection.channels.len(),
+            subframes,
+            values: projection
+                .channels
+                .iter()
+                .flat_map(|channel| {
+                    (0..subframes).map(move |subframe| self.sample(frame, *channel, subframe))
+                })
+                .map(|v| v.unwrap_or(f32::NAN))
+                .collect(),
+        };
+        (PointData { values }, Some(analog))
+    }
+
+    fn decode_analog(&self, bytes: &[u8]) -> Option<AnalogData> {
+        let analog_n = self.analog_counts;
+        if analog_n == 0 {
+            return None;
+        }
+
+        let samples = bytes
+            .chunks_exact(self.word_length())
+            .map(|arr| self.read_sample(arr));
+
+        // samples are stored subframe by subframe with the channels interleaved.
+        let (channels, subframes) = self.analog_scaling.layout(analog_n, self.analog_per_frame);
+        let mut values = vec![0_f32; analog_n];
+        for (idx, sample) in samples.enumerate() {
+            let (subframe, channel) = (idx / channels, idx % channels);
+            values[channel * subframes + subframe] = sample;
+        }
+        self.analog_scaling.apply(&mut values, subframes);
+
+        let mut analog = AnalogData {
+            channels,
+            subframes,
+            values,
+        };
+        if let Some(baseline) = self.baseline.as_ref() {
+            baseline.apply(&mut analog);
+        }
+        Some(analog)
+    }
+}
@@ -1,17 +1,40 @@
 use anyhow::Result;
 use std::collections::HashMap;
 
-use std::cell::RefCell;
-use std::fs::File;
 use std::io;
 use std::io::prelude::*;
 use std::io::Cursor;
 use std::io::SeekFrom;
 use std::mem;
-use std::rc::Rc;
+use std::ops::{Bound, Range, RangeBounds, RangeInclusive};
 use std::slice;
+use std::sync::{Mutex, MutexGuard};
 use thiserror::Error;
 
+#[cfg(feature = "tokio")]
+pub mod async_adapter;
+pub mod events;
+pub mod force_plate;
+mod frame;
+pub mod processor;
+pub mod projection;
+pub mod stream;
+pub mod trial;
+pub mod view;
+pub mod writer;
+
+#[cfg(feature = "tokio")]
+pub use async_adapter::AsyncC3dAdapter;
+pub use events::{Event, EventSet};
+pub use force_plate::{Baseline, ForcePlatform, PlateType, Wrench};
+use frame::{FrameDecoder, Projection};
+pub use processor::Processor;
+pub use projection::ProjectionBuilder;
+pub use stream::C3dStream;
+pub use trial::Trial;
+pub use view::{C3dSlice, FrameView};
+pub use writer::C3dWriter;
+
 #[derive(Error, Debug)]
 pub enum ParserError {
     #[error("magic word not unmatched, might not be a c3d file")]
@@ -22,18 +45,72 @@ pub enum ParserError {
     IoError(#[from] io::Error),
     #[error("missing header/parameter")]
     MissingField,
+    #[error("frame does not match the layout declared by the header")]
+    FrameLayoutMismatch,
+    #[error("parameter section too large, needs {0} blocks")]
+    ParameterBlockOverflow(usize),
+    #[error("{field} of parameter record {name:?} is {length} bytes long, more than it can store")]
+    RecordTooLong {
+        name: String,
+        field: &'static str,
+        length: usize,
+    },
+    #[error("unexpected end of {section} at byte {offset}")]
+    Truncated { section: &'static str, offset: u64 },
+    #[error(
+        "invalid parameter record at byte {offset} (group id {group}, name {name:?}): {reason}"
+    )]
+    InvalidParameter {
+        offset: u64,
+        group: i8,
+        name: Option<String>,
+        reason: &'static str,
+    },
+    #[error("no group id left for group {0:?}")]
+    GroupIdsExhausted(String),
+    #[error("invalid header: {0}")]
+    InvalidHeader(&'static str),
+    #[error("{0} events do not fit in the EVENT group")]
+    TooManyEvents(usize),
+    #[error("event {field} of {length} characters does not fit in the EVENT group")]
+    EventStringTooLong { field: &'static str, length: usize },
+    #[error("another reader still holds the file")]
+    ReaderInUse,
+    #[error("frame {0} is outside of the trial")]
+    FrameOutOfRange(u32),
+    #[error("invalid force platform {index}: {reason}")]
+    InvalidForcePlatform { index: usize, reason: &'static str },
+    #[error("no point or analog label matches {0:?}")]
+    UnknownLabel(String),
+    #[error("analog channel {0} is not stored in the file")]
+    ChannelOutOfRange(usize),
+    #[error("missing parameter {0}")]
+    MissingParameter(String),
+    #[error("parameter {key} holds {found} values, expected {expected}")]
+    WrongParameterType {
+        key: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+    #[error("{section} starts at byte {offset} but the stream is already at byte {position}")]
+    BackwardSeek {
+        section: &'static str,
+        offset: u64,
+        position: u64,
+    },
 }
 
 pub struct C3dAdapter<T: Read + Seek> {
     pub header: Option<HeaderBlock>,
     pub parameter: Option<ParameterBlock>,
-    handle: Rc<RefCell<T>>,
+    // taken by the reader while it is alive.
+    handle: Mutex<Option<T>>,
 }
 
 impl<T: Read + Seek> C3dAdapter<T> {
     pub fn new(mut file: T) -> Result<Self, ParserError> {
         file.seek(SeekFrom::Start(0))?;
-        let handle = Rc::new(RefCell::new(file));
+        let handle = Mutex::new(Some(file));
 
         Ok(C3dAdapter {
             header: None,
@@ -43,19 +120,15 @@ impl<T: Read + Seek> C3dAdapter<T> {
     }
 
     pub fn construct(mut self) -> Result<Self, ParserError> {
-        let header = HeaderBlock::from_reader(&mut *self.handle.borrow_mut());
-        let parameter = ParameterBlock::from_reader(&mut *self.handle.borrow_mut());
-
-        // 0x50 if header is of correct format.
-        if header.magic_word != 0x50 {
-            return Err(ParserError::UnmatchMagic);
-        }
-
-        // 0x50 + 4 if parameter is of correct format.
-        if parameter.header.magic_word != 0x50 + 4 {
-            dbg!(parameter.header.magic_word);
-            return Err(ParserError::UnmatchMagic);
-        }
+        let handle = self
+            .handle
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_mut()
+            .ok_or(ParserError::ReaderInUse)?;
+        let header = HeaderBlock::from_reader(handle)?;
+        let parameter = ParameterBlock::from_reader(handle)?;
+        let header = validate(header, &parameter)?;
 
         self.header.replace(header);
         self.parameter.replace(parameter);
@@ -64,246 +137,515 @@ impl<T: Read + Seek> C3dAdapter<T> {
     }
 }
 
+/// check the magic words and decode the header for the processor of the parameter block.
+pub(crate) fn validate(
+    header: HeaderBlock,
+    parameter: &ParameterBlock,
+) -> Result<HeaderBlock, ParserError> {
+    // 0x50 if header is of correct format.
+    if header.magic_word != 0x50 {
+        return Err(ParserError::UnmatchMagic);
+    }
+
+    // 0x50 + 4/5/6 for Intel/DEC/MIPS if parameter is of correct format.
+    let processor =
+        Processor::from_magic(parameter.header.magic_word).ok_or(ParserError::UnmatchMagic)?;
+    let header = header.decode(processor);
+
+    if header.data_start == 0 {
+        return Err(ParserError::InvalidHeader("data section starts at block 0"));
+    }
+
+    Ok(header)
+}
+
 pub struct C3dReader<'a, R: Read + Seek> {
-    header: &'a HeaderBlock,
-    parameter: &'a ParameterBlock,
-    handle: std::cell::RefMut<'a, R>,
-    points_buffer: Vec<u8>,
-    analog_buffer: Vec<u8>,
-    frame_idx: u16,
-    analog_unsigned: bool,
-    analog_offset: Option<Vec<f32>>,
-    analog_scale: Option<Vec<f32>>,
-    analog_gen_scale: Option<f32>,
+    // returned to the adapter on drop.
+    handle: Option<R>,
+    slot: &'a Mutex<Option<R>>,
+    buffer: Vec<u8>,
+    decoder: FrameDecoder,
+    // only these markers and channels are decoded when set.
+    projection: Option<Projection>,
+    // byte offset of the first frame.
+    data_offset: u64,
+    frames: RangeInclusive<u32>,
+    // frames front..back are left to read.
+    front: u64,
+    back: u64,
+    // frames before this one are stored in full, the data section may be cut short.
+    available: u64,
+    // frame the stream is positioned at, None if unknown.
+    position: Option<u64>,
 }
 
 impl<'a, R: Read + Seek> C3dReader<'a, R> {
     pub fn new(
         header: &'a HeaderBlock,
         parameter: &'a ParameterBlock,
-        mut handle: std::cell::RefMut<'a, R>,
+        slot: &'a Mutex<Option<R>>,
     ) -> Result<Self, ParserError> {
-        (*handle).seek(SeekFrom::Start((header.data_start as u64 - 1) * 512))?;
-        let points_buffer: Vec<u8> = vec![];
-        let analog_buffer: Vec<u8> = vec![];
+        let handle = lock(slot).take().ok_or(ParserError::ReaderInUse)?;
+        let frames = frame_range(header, parameter);
+
+        let mut reader = C3dReader {
+            handle: Some(handle),
+            slot,
+            buffer: vec![],
+            decoder: FrameDecoder::new(header, parameter),
+            projection: None,
+            data_offset: (header.data_start as u64).saturating_sub(1) * 512,
+            front: *frames.start() as u64,
+            back: *frames.end() as u64 + 1,
+            available: *frames.end() as u64 + 1,
+            frames,
+            position: None,
+        };
 
-        let analog_unsigned = parameter
-            .get("ANALOG:FORMAT")
-            .map(|param| {
-                param
-                    .parameter_data
-                    .values
-                    .iter()
-                    .filter_map(|v| v.as_char())
-                    .filter(|c| !c.is_whitespace())
-                    .collect::<String>()
-            })
-            .and(Some("UNSIGNED"))
-            .is_some();
-
-        let analog_offset = parameter.get("ANALOG:OFFSET").map(|v| {
-            v.parameter_data
-                .values
-                .iter()
-                .filter_map(|arr| arr.as_i16())
-                .map(|a| *a as f32)
-                .collect::<Vec<f32>>()
-        });
+        reader.available = reader.available_frames()?;
+        reader.back = reader.available;
 
-        let analog_scale = parameter.get("ANALOG:SCALE").map(|v| {
-            v.parameter_data
-                .values
-                .iter()
-                .filter_map(|arr| arr.as_f32())
-                .map(|a| *a)
-                .collect::<Vec<f32>>()
-        });
+        // the handle goes back to the adapter if seeking fails and the reader is dropped, a
+        // trial without frames has nothing to seek to.
+        if !reader.frames.is_empty() {
+            reader.seek_frame(*reader.frames.start())?;
+        }
 
-        let analog_gen_scale = parameter
-            .get("ANALOG:GEN_SCALE")
-            .map(|v| {
-                v.parameter_data
-                    .values
-                    .iter()
-                    .filter_map(|arr| arr.as_f32())
-                    .map(|a| *a)
-                    .next()
-            })
-            .flatten();
+        Ok(reader)
+    }
 
-        log::debug!("analog offsets: {:?}", analog_offset);
-        log::debug!("scale factors: {:?}", analog_scale);
-        log::debug!("genral scale factor: {:?}", analog_gen_scale);
+    /// subtract the force plate baseline from every analog frame read afterwards.
+    pub fn with_baseline(mut self, baseline: Baseline) -> Self {
+        self.decoder.baseline.replace(baseline);
+        self
+    }
 
-        Ok(C3dReader {
-            header,
-            parameter,
-            handle,
-            points_buffer,
-            analog_buffer,
-            frame_idx: header.frame_first,
-            analog_unsigned,
-            analog_offset,
-            analog_scale,
-            analog_gen_scale,
+    /// continue reading from `frame` up to the last frame of the trial.
+    pub fn seek_frame(&mut self, frame: u32) -> Result<(), ParserError> {
+        if !self.frames.contains(&frame) {
+            return Err(ParserError::FrameOutOfRange(frame));
+        }
+
+        let offset = self.frame_offset(frame as u64) + self.span().start as u64;
+        if let Some(handle) = self.handle.as_mut() {
+            handle.seek(SeekFrom::Start(offset))?;
+        }
+        self.position = Some(frame as u64);
+        self.front = frame as u64;
+        self.back = self.available;
+
+        Ok(())
+    }
+
+    /// read the frames of `range` only.
+    pub(crate) fn restrict(&mut self, range: impl RangeBounds<u32>) -> Result<(), ParserError> {
+        let start = match range.start_bound() {
+            Bound::Included(v) => *v as u64,
+            Bound::Excluded(v) => *v as u64 + 1,
+            Bound::Unbounded => *self.frames.start() as u64,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(v) => *v as u64 + 1,
+            Bound::Excluded(v) => *v as u64,
+            Bound::Unbounded => *self.frames.end() as u64 + 1,
+        };
+
+        let (first, last) = (*self.frames.start() as u64, *self.frames.end() as u64);
+        if start < first || start > last + 1 {
+            return Err(ParserError::FrameOutOfRange(start as u32));
+        }
+        if end > last + 1 {
+            return Err(ParserError::FrameOutOfRange(end.saturating_sub(1) as u32));
+        }
+
+        if start <= last {
+            self.seek_frame(start as u32)?;
+        }
+        self.front = start;
+        self.back = end.min(self.available).max(start);
+
+        Ok(())
+    }
+
+    /// the raw bytes of the frames left to read, cut short at the end of the stream.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn read_remaining(&mut self) -> Result<Vec<u8>, ParserError> {
+        let length = self.back.saturating_sub(self.front) * self.decoder.frame_length() as u64;
+        let offset = self.frame_offset(self.front);
+        let handle = self.handle.as_mut().ok_or(ParserError::ReaderInUse)?;
+
+        handle.seek(SeekFrom::Start(offset))?;
+        let mut bytes = vec![];
+        handle.by_ref().take(length).read_to_end(&mut bytes)?;
+
+        self.position = None;
+        self.front = self.back;
+        Ok(bytes)
+    }
+
+    /// decode only the markers and channels of the projection.
+    pub(crate) fn project(&mut self, projection: Projection) {
+        self.projection.replace(projection);
+        self.position = None;
+    }
+
+    /// bytes of each frame that are read, the whole frame unless projected.
+    fn span(&self) -> Range<usize> {
+        match self.projection.as_ref() {
+            Some(projection) => self.decoder.span(projection),
+            None => 0..self.decoder.frame_length(),
+        }
+    }
+
+    /// end of the frames stored in full, so that the length of the reader holds.
+    fn available_frames(&mut self) -> Result<u64, ParserError> {
+        let (first, end) = (*self.frames.start() as u64, *self.frames.end() as u64 + 1);
+        let frame_length = self.decoder.frame_length() as u64;
+        let handle = self.handle.as_mut().ok_or(ParserError::ReaderInUse)?;
+
+        let stored = handle
+            .seek(SeekFrom::End(0))?
+            .saturating_sub(self.data_offset);
+        self.position = None;
+        Ok(match stored.checked_div(frame_length) {
+            Some(complete) => end.min(first + complete),
+            // frames without points or analog samples take no bytes.
+            None => end,
         })
     }
-}
 
-impl<'a, R: Read + Seek> Iterator for C3dReader<'a, R> {
-    type Item = (u16, PointData, Option<AnalogData>);
+    fn frame_offset(&self, frame: u64) -> u64 {
+        let first = *self.frames.start() as u64;
+        self.data_offset + frame.saturating_sub(first) * self.decoder.frame_length() as u64
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.frame_idx > self.header.frame_last {
+    fn read_at(&mut self, frame: u64) -> Option<(PointData, Option<AnalogData>)> {
+        let span = self.span();
+        let frame_length = self.decoder.frame_length();
+        let offset = self.frame_offset(frame) + span.start as u64;
+        let handle = self.handle.as_mut()?;
+
+        // the stream sits at the start of the span of `position`.
+        if self.position != Some(frame) {
+            self.position = None;
+            handle.seek(SeekFrom::Start(offset)).ok()?;
+        }
+
+        self.buffer.resize(frame_length, 0_u8);
+        if handle.read_exact(&mut self.buffer[span.clone()]).is_err() {
+            self.position = None;
             return None;
         }
+        // bytes up to the span of the next frame. Short gaps are read through, as seeking
+        // would throw away the buffer of a `BufReader`.
+        let skipped = (frame_length - span.len()) as u64;
+        let at_next = match skipped {
+            0 => true,
+            1..=SKIP_BY_READING => io::copy(&mut handle.by_ref().take(skipped), &mut io::sink())
+                .map(|n| n == skipped)
+                .unwrap_or(false),
+            _ => handle.seek(SeekFrom::Current(skipped as i64)).is_ok(),
+        };
+        // the frame is complete even when the stream ends before the next one.
+        self.position = if at_next { Some(frame + 1) } else { None };
 
-        let point_scale = self.header.scale;
-        let is_float = point_scale <= 0.0;
+        match self.projection.as_ref() {
+            Some(projection) => Some(self.decoder.project(&self.buffer, projection)),
+            None => Some(self.decoder.decode(&self.buffer)),
+        }
+    }
+}
 
-        let point_data_length = if is_float { 4 } else { 2 };
+/// gaps between projected spans up to this many bytes are read rather than seeked over.
+const SKIP_BY_READING: u64 = 8 * 1024;
 
-        let point_scale = if is_float { 1.0 } else { point_scale.abs() };
+/// First and last frame of the trial. The header only holds 16 bit indices, longer captures
+/// record the real range in TRIAL:ACTUAL_START_FIELD/ACTUAL_END_FIELD or a float POINT:FRAMES.
+pub(crate) fn frame_range(header: &HeaderBlock, parameter: &ParameterBlock) -> RangeInclusive<u32> {
+    // two words each, low word first, both unsigned.
+    let field = |key: &str| {
+        parameter
+            .get(key)
+            .and_then(|v| v.values::<i16>())
+            .filter(|v| v.len() >= 2)
+            .map(|v| v[0] as u16 as u32 + ((v[1] as u16 as u32) << 16))
+    };
+
+    let first = header.frame_first as u32;
+    let last = header.frame_last as u32;
+
+    if let (Some(start), Some(end)) = (
+        field("TRIAL:ACTUAL_START_FIELD"),
+        field("TRIAL:ACTUAL_END_FIELD"),
+    ) {
+        if start > 0 && end >= start {
+            return start..=end;
+        }
+    }
 
-        let analog_data_length = if is_float { 4 } else { 2 };
+    let frames = parameter
+        .get("POINT:FRAMES")
+        .and_then(|v| match v.parameter_data {
+            ParamData::Float32(ref v) => v.first().map(|v| *v as u32),
+            ParamData::Int16(ref v) => v.first().map(|v| *v as u16 as u32),
+            _ => None,
+        });
 
-        let points_n = 4 * self.header.point_counts;
-        let analog_n = self.header.analog_counts;
+    // a count running past u32::MAX is corrupt, the header range is kept.
+    let end = frames
+        .filter(|frames| *frames > 0 && *frames > last.saturating_sub(first) + 1)
+        .and_then(|frames| first.max(1).checked_add(frames - 1));
 
-        self.points_buffer
-            .resize((points_n * point_data_length) as usize, 0_u8);
+    match end {
+        Some(end) => first.max(1)..=end,
+        None => first..=last,
+    }
+}
 
-        if self.handle.read_exact(&mut self.points_buffer[..]).is_err() {
-            return None;
+/// How analog samples are stored in integer files, from ANALOG:FORMAT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AnalogFormat {
+    /// two's complement samples, the default when the parameter is missing.
+    #[default]
+    Signed,
+    /// offset binary samples, zero volts sits at the middle of the ADC range.
+    Unsigned,
+}
+
+impl AnalogFormat {
+    pub fn from_parameter(parameter: &ParameterBlock) -> Self {
+        let format = parameter
+            .get("ANALOG:FORMAT")
+            .and_then(|param| param.as_strings())
+            .and_then(|v| v.into_iter().next())
+            .map(|v| v.to_uppercase());
+
+        match format.as_deref() {
+            Some("UNSIGNED") => AnalogFormat::Unsigned,
+            Some("SIGNED") | Some("") | None => AnalogFormat::Signed,
+            Some(other) => {
+                log::warn!("unknown analog format {:?}, assuming signed", other);
+                AnalogFormat::Signed
+            }
         }
+    }
+}
 
-        let values = self
-            .points_buffer
-            .chunks_exact(4 * point_data_length as usize)
-            .map(|arr| {
-                let mut points_vec = [0_f32; 5];
-                let raw_vec = arr
-                    .chunks_exact(point_data_length as usize)
-                    .filter_map(|arr| {
-                        Some(if is_float {
-                            let mut buf = [0_u8; 4];
-                            arr.clone().read_exact(&mut buf).unwrap();
-                            f32::from_le_bytes(buf)
-                        } else {
-                            let mut buf = [0_u8; 2];
-                            arr.clone().read_exact(&mut buf).unwrap();
-                            i16::from_le_bytes(buf) as f32 * point_scale
-                        })
+/// Conversion between stored analog samples and real world values, shared by reader and writer.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AnalogScaling {
+    pub(crate) format: AnalogFormat,
+    pub(crate) used: Option<usize>,
+    pub(crate) offset: Option<Vec<f32>>,
+    pub(crate) scale: Option<Vec<f32>>,
+    pub(crate) gen_scale: Option<f32>,
+}
+
+impl AnalogScaling {
+    pub(crate) fn from_parameter(parameter: &ParameterBlock) -> Self {
+        let format = AnalogFormat::from_parameter(parameter);
+
+        // resolution of the ADC, 16 bits fill the whole word.
+        let bits = parameter
+            .get("ANALOG:BITS")
+            .and_then(|v| v.get_scalar::<i16>())
+            .filter(|v| (1..=16).contains(v));
+
+        let used = parameter
+            .get("ANALOG:USED")
+            .and_then(|v| v.get_scalar::<i16>())
+            .map(|v| v as u16 as usize);
+
+        let offset = parameter
+            .get("ANALOG:OFFSET")
+            .map(|v| {
+                v.values::<i16>()
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|a| match format {
+                        // offsets of unsigned samples live in the same 0..65535 range.
+                        AnalogFormat::Unsigned => *a as u16 as f32,
+                        AnalogFormat::Signed => *a as f32,
                     })
-                    .collect::<Vec<f32>>();
-                points_vec[..4].copy_from_slice(&raw_vec);
-                points_vec
+                    .collect::<Vec<f32>>()
             })
-            .map(|mut arr| {
-                let fourth = &arr[3];
-
-                if *fourth <= -0.01_f32 {
-                    arr[3..5].iter_mut().for_each(|v| {
-                        *v = -0.01_f32;
-                    });
-                } else {
-                    let err = arr[3] as i16;
-                    // the right eight bits are for error estimation.
-                    arr[3] = (err & 0xff) as f32 * self.header.scale.abs();
-
-                    // the left eight bits are for reporting total number of camera that obsered
-                    arr[4] = (8..17)
-                        .map(|idx| {
-                            // camera mask to check wheter the bits are sets
-                            let mask = idx << 8;
-
-                            (err & mask) as f32
-                        })
-                        .sum();
+            .or_else(|| match (format, bits, used) {
+                // without explicit offsets, unsigned samples are offset binary around half range.
+                (AnalogFormat::Unsigned, Some(bits), Some(used)) => {
+                    Some(vec![(1_u32 << (bits - 1)) as f32; used])
                 }
-                arr
-            })
-            .collect::<Vec<_>>();
+                _ => None,
+            });
+
+        let scale = parameter
+            .get("ANALOG:SCALE")
+            .map(|v| v.as_f32_vec().unwrap_or_default());
+
+        let gen_scale = parameter
+            .get("ANALOG:GEN_SCALE")
+            .and_then(|v| v.get_scalar::<f32>());
+
+        log::debug!("analog format: {:?}, bits: {:?}", format, bits);
+        log::debug!("analog channels: {:?}", used);
+        log::debug!("analog offsets: {:?}", offset);
+        log::debug!("scale factors: {:?}", scale);
+        log::debug!("genral scale factor: {:?}", gen_scale);
+
+        AnalogScaling {
+            format,
+            used,
+            offset,
+            scale,
+            gen_scale,
+        }
+    }
 
-        let point_data = PointData { values };
-        let analog_data = if analog_n > 0 {
-            self.analog_buffer
-                .resize((analog_n * analog_data_length) as usize, 0_u8);
-            if self.handle.read_exact(&mut self.analog_buffer[..]).is_err() {
-                return None;
+    /// (channels, subframes) of the `analog_counts` samples stored per point frame.
+    ///
+    /// The channel count comes from ANALOG:USED, falling back to the samples per frame recorded in
+    /// the header when it is missing or doesn't divide the sample count.
+    pub(crate) fn layout(&self, analog_counts: usize, analog_per_frame: usize) -> (usize, usize) {
+        match self.used {
+            Some(used) if used > 0 && analog_counts % used == 0 => (used, analog_counts / used),
+            _ if analog_per_frame > 0 && analog_counts % analog_per_frame == 0 => {
+                (analog_counts / analog_per_frame, analog_per_frame)
             }
+            _ => (analog_counts, 1),
+        }
+    }
 
-            let mut values: Vec<f32> = self
-                .analog_buffer
-                .chunks_exact(analog_data_length as usize)
-                .map(|arr| {
-                    if is_float {
-                        let mut buf = [0_u8; 4];
-                        (arr.clone()).read_exact(&mut buf).unwrap();
-                        f32::from_le_bytes(buf)
-                    } else {
-                        let mut buf = [0_u8; 2];
-                        if self.analog_unsigned {
-                            (arr.clone()).read_exact(&mut buf).unwrap();
-                            u16::from_le_bytes(buf) as f32
-                        } else {
-                            (arr.clone()).read_exact(&mut buf).unwrap();
-                            i16::from_le_bytes(buf) as f32
-                        }
-                    }
-                })
-                .collect();
-            if let Some(offsets) = self.analog_offset.as_ref() {
-                values.iter_mut().zip(offsets.iter()).for_each(|(v, off)| {
-                    *v -= *off;
-                });
+    /// stored samples -> real world values, `values` holds each channel's subframes in turn.
+    pub(crate) fn apply(&self, values: &mut [f32], subframes: usize) {
+        for (channel, values) in values.chunks_mut(subframes.max(1)).enumerate() {
+            for v in values.iter_mut() {
+                *v = self.value(channel, *v);
             }
+        }
+    }
+
+    /// a single stored sample of the channel -> real world value.
+    pub(crate) fn value(&self, channel: usize, mut v: f32) -> f32 {
+        if let Some(offset) = self.offset.as_ref().and_then(|v| v.get(channel)) {
+            v -= *offset;
+        }
+        if let Some(scale) = self.scale.as_ref().and_then(|v| v.get(channel)) {
+            v *= *scale;
+        }
+        if let Some(gen_scale) = self.gen_scale.as_ref() {
+            v *= *gen_scale;
+        }
+        v
+    }
+
+    /// real world values -> stored samples, the inverse of `apply`.
+    pub(crate) fn revert(&self, values: &mut [f32], subframes: usize) {
+        for (channel, values) in values.chunks_mut(subframes.max(1)).enumerate() {
+            let offset = self.offset.as_ref().and_then(|v| v.get(channel));
+            let scale = self.scale.as_ref().and_then(|v| v.get(channel));
 
-            if let Some(scales) = self.analog_scale.as_ref() {
-                values.iter_mut().zip(scales.iter()).for_each(|(v, off)| {
-                    *v *= *off;
-                });
+            for v in values.iter_mut() {
+                if let Some(gen_scale) = self.gen_scale.as_ref() {
+                    *v /= *gen_scale;
+                }
+                if let Some(scale) = scale {
+                    *v /= *scale;
+                }
+                // skipping zero offsets keeps negative zeros intact.
+                if let Some(offset) = offset.filter(|off| **off != 0.0) {
+                    *v += *offset;
+                }
             }
+        }
+    }
+}
 
-            if let Some(gen_scale) = self.analog_gen_scale.as_ref() {
-                values.iter_mut().for_each(|v| {
-                    *v *= *gen_scale;
-                });
+impl<'a, R: Read + Seek> Drop for C3dReader<'a, R> {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            lock(self.slot).replace(handle);
+        }
+    }
+}
+
+/// a panicking reader cannot leave the handle half updated, so poisoning is ignored.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl<'a, R: Read + Seek> Iterator for C3dReader<'a, R> {
+    type Item = (u32, PointData, Option<AnalogData>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
+
+        let frame = self.front;
+        match self.read_at(frame) {
+            Some((point_data, analog_data)) => {
+                self.front += 1;
+                Some((frame as u32, point_data, analog_data))
             }
+            // a truncated data section ends the iteration.
+            None => {
+                self.front = self.back;
+                None
+            }
+        }
+    }
 
-            Some(AnalogData { values })
-        } else {
-            None
-        };
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back.saturating_sub(self.front) as usize;
+        (len, Some(len))
+    }
+}
 
-        let frame_idx = self.frame_idx;
-        self.frame_idx += 1;
+impl<'a, R: Read + Seek> DoubleEndedIterator for C3dReader<'a, R> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
 
-        Some((frame_idx, point_data, analog_data))
+        let frame = self.back - 1;
+        match self.read_at(frame) {
+            Some((point_data, analog_data)) => {
+                self.back -= 1;
+                Some((frame as u32, point_data, analog_data))
+            }
+            None => {
+                self.back = self.front;
+                None
+            }
+        }
     }
 }
 
+impl<'a, R: Read + Seek> ExactSizeIterator for C3dReader<'a, R> {}
+
+/// labels with every whitespace removed, `"Raw.Pin 1"` is read as `"Raw.Pin1"`.
+fn compact_labels(param: &ParameterFormat) -> Option<Vec<String>> {
+    let labels = param.as_strings()?;
+    Some(
+        labels
+            .iter()
+            .map(|v| v.chars().filter(|c| !c.is_whitespace()).collect())
+            .collect(),
+    )
+}
+
 impl<T: Read + Seek> C3dAdapter<T> {
+    /// `POINT:LABELS` with every whitespace removed, `as_strings` keeps the inner ones.
     pub fn get_point_labels(&self) -> Option<Vec<String>> {
         let mut rv = None;
         if let Some(parameter) = self.parameter.as_ref() {
-            rv = parameter.get("POINT:LABELS").map(|param| {
-                param
-                    .parameter_data
-                    .values
-                    .chunks_exact(param.dimensions[0] as usize)
-                    .map(|arr| {
-                        arr.iter()
-                            .filter_map(|v| v.as_char())
-                            .filter(|c| !c.is_whitespace())
-                            .collect::<String>()
-                    })
-                    .collect::<Vec<String>>()
-            });
+            rv = parameter
+                .get("POINT:LABELS")
+                .map(|param| compact_labels(param).unwrap_or_default());
         }
         rv
     }
 
+    /// the `ANALOG:LABELS*` parameters in turn, with every whitespace removed.
     pub fn get_analog_labels(&self) -> Option<Vec<String>> {
         let mut rv = None;
         if let Some(parameter) = self.parameter.as_ref() {
@@ -311,7 +653,7 @@ impl<T: Read + Seek> C3dAdapter<T> {
                 let mut keys = analog
                     .params
                     .keys()
-                    .filter_map(|v| if v.contains("LABEL") { Some(v) } else { None })
+                    .filter(|v| v.contains("LABEL"))
                     .collect::<Vec<_>>();
 
                 keys.sort();
@@ -319,19 +661,9 @@ impl<T: Read + Seek> C3dAdapter<T> {
                 rv = Some(
                     keys.iter()
                         .filter_map(|v| {
-                            parameter.get(&format!("ANALOG:{}", v)).map(|param| {
-                                param
-                                    .parameter_data
-                                    .values
-                                    .chunks_exact(param.dimensions[0] as usize)
-                                    .map(|arr| {
-                                        arr.iter()
-                                            .filter_map(|c| c.as_char())
-                                            .filter(|c| !c.is_whitespace())
-                                            .collect::<String>()
-                                    })
-                                    .collect::<Vec<String>>()
-                            })
+                            parameter
+                                .get(format!("ANALOG:{}", v))
+                                .and_then(compact_labels)
                         })
                         .flatten()
                         .collect::<Vec<_>>(),
@@ -341,11 +673,104 @@ impl<T: Read + Seek> C3dAdapter<T> {
         rv
     }
 
+    /// events stored in the header, with frames computed from the point rate.
+    pub fn events(&self) -> Option<Vec<Event>> {
+        self.header.as_ref().map(events::from_header)
+    }
+
+    /// events of both the header and the EVENT group, ordered by time.
+    pub fn event_set(&self) -> Option<EventSet> {
+        Some(EventSet::from_parameter(
+            self.header.as_ref()?,
+            self.parameter.as_ref()?,
+        ))
+    }
+
+    /// plates of the FORCE_PLATFORM group, see `ForcePlatform::wrench` for the per frame loads.
+    pub fn force_platforms(&self) -> Result<Vec<ForcePlatform>, ParserError> {
+        let parameter = self.parameter.as_ref().ok_or(ParserError::MissingField)?;
+        ForcePlatform::from_parameter(parameter)
+    }
+
+    /// force plate baseline over `range`, or over FORCE_PLATFORM:ZERO if no range is given.
+    /// Empty if zeroing is disabled, pass it to `C3dReader::with_baseline`.
+    pub fn force_plate_baseline(
+        &self,
+        range: Option<RangeInclusive<u32>>,
+    ) -> Result<Baseline, ParserError> {
+        let parameter = self.parameter.as_ref().ok_or(ParserError::MissingField)?;
+        let range = match range.or_else(|| Baseline::zero_range(parameter)) {
+            Some(range) => range,
+            None => return Ok(Baseline::default()),
+        };
+
+        let plates = ForcePlatform::from_parameter(parameter)?;
+        let trial = self.frames().ok_or(ParserError::MissingField)?;
+        let start = *range.start().max(trial.start());
+        let end = *range.end().min(trial.end());
+        if start > end {
+            return Ok(Baseline::default());
+        }
+
+        let frames = self
+            .read_range(start..=end)?
+            .filter_map(|(_, _, analog)| analog)
+            .collect::<Vec<_>>();
+
+        Ok(Baseline::compute(&plates, &frames))
+    }
+
+    /// load every frame into columnar arrays.
+    pub fn trial(&self) -> Result<Trial, ParserError> {
+        Trial::from_adapter(self)
+    }
+
+    /// a single frame, counted like the indices of the reader.
+    pub fn read_frame(
+        &self,
+        frame: u32,
+    ) -> Result<(u32, PointData, Option<AnalogData>), ParserError> {
+        let mut reader = self.reader()?;
+        reader.seek_frame(frame)?;
+        let offset = reader.frame_offset(frame as u64);
+
+        reader.next().ok_or(ParserError::Truncated {
+            section: "data section",
+            offset,
+        })
+    }
+
+    /// a reader over the frames of `range`, e.g. `adapter.read_range(100..200)?`.
+    pub fn read_range(
+        &self,
+        range: impl RangeBounds<u32>,
+    ) -> Result<C3dReader<'_, T>, ParserError> {
+        let mut reader = self.reader()?;
+        reader.restrict(range)?;
+        Ok(reader)
+    }
+
+    /// first to last frame, including captures longer than the 16 bit header fields allow.
+    pub fn frames(&self) -> Option<RangeInclusive<u32>> {
+        Some(frame_range(self.header.as_ref()?, self.parameter.as_ref()?))
+    }
+
+    /// decode every frame on the rayon thread pool, giving the same trial as
+    /// `Trial::from_adapter`.
+    #[cfg(feature = "rayon")]
+    pub fn read_all_parallel(&self) -> Result<Trial, ParserError> {
+        Trial::from_adapter_parallel(self)
+    }
+
+    /// a reader decoding only the markers and analog channels picked on the builder.
+    pub fn projection(&self) -> ProjectionBuilder<'_, T> {
+        ProjectionBuilder::new(self)
+    }
+
     pub fn reader<'a>(&'a self) -> Result<C3dReader<'a, T>, ParserError> {
         if let Some(header) = self.header.as_ref() {
             if let Some(parameter) = self.parameter.as_ref() {
-                let handle = self.handle.borrow_mut();
-                return C3dReader::new(header, parameter, handle);
+                return C3dReader::new(header, parameter, &self.handle);
             }
         }
         Err(ParserError::MissingField)
@@ -369,230 +794,706 @@ pub struct HeaderBlock {
     pub analog_per_frame: u16,
     pub frame_rate: f32,
     reserved: [u8; 274],
-    event_lables_long: u16,
-    event_counts: u16,
+    // 12345 if event labels are 4 characters long.
+    pub(crate) event_lables_long: u16,
+    pub(crate) event_counts: u16,
     reserved_two: u16,
-    event_times: [f32; 18],
-    event_display_flags: [u8; 18],
+    pub(crate) event_times: [f32; 18],
+    pub(crate) event_display_flags: [u8; 18],
     reserved_three: u16,
-    event_labels: [u8; 72],
+    pub(crate) event_labels: [u8; 72],
     reserved_four: [u8; 44],
 }
 
-trait FromReader {
-    fn from_reader<R: Read + Seek>(r: &mut R) -> Self;
+impl Default for HeaderBlock {
+    fn default() -> Self {
+        let mut header: HeaderBlock = unsafe { mem::zeroed() };
+        header.parameter_start = 2;
+        header.magic_word = 0x50;
+        header
+    }
+}
+
+impl HeaderBlock {
+    /// reinterpret the fields copied verbatim from disk according to the processor type.
+    fn decode(&self, processor: Processor) -> Self {
+        self.convert(
+            |v| processor.read_u16(v.to_ne_bytes()),
+            |v| processor.read_f32(v.to_ne_bytes()),
+        )
+    }
+
+    /// the inverse of `decode`, fields hold the bytes to be copied verbatim to disk.
+    pub(crate) fn encode(&self, processor: Processor) -> Self {
+        self.convert(
+            |v| u16::from_ne_bytes(processor.write_u16(v)),
+            |v| f32::from_ne_bytes(processor.write_f32(v)),
+        )
+    }
+
+    fn convert(&self, word: impl Fn(u16) -> u16, float: impl Fn(f32) -> f32) -> Self {
+        let mut header = *self;
+        header.point_counts = word(self.point_counts);
+        header.analog_counts = word(self.analog_counts);
+        header.frame_first = word(self.frame_first);
+        header.frame_last = word(self.frame_last);
+        header.max_gap = word(self.max_gap);
+        header.scale = float(self.scale);
+        header.data_start = word(self.data_start);
+        header.analog_per_frame = word(self.analog_per_frame);
+        header.frame_rate = float(self.frame_rate);
+        header.event_lables_long = word(self.event_lables_long);
+        header.event_counts = word(self.event_counts);
+        header.reserved_two = word(self.reserved_two);
+        let mut event_times = self.event_times;
+        event_times.iter_mut().for_each(|v| *v = float(*v));
+        header.event_times = event_times;
+        header.reserved_three = word(self.reserved_three);
+        header
+    }
+}
+
+trait FromReader: Sized {
+    fn from_reader<R: Read + Seek>(r: &mut R) -> Result<Self, ParserError>;
 }
 
 impl FromReader for HeaderBlock {
-    fn from_reader<R: Read + Seek>(r: &mut R) -> Self {
+    fn from_reader<R: Read + Seek>(r: &mut R) -> Result<Self, ParserError> {
         let mut header: HeaderBlock = unsafe { mem::zeroed() };
         let header_size = mem::size_of::<HeaderBlock>();
+        let offset = r.stream_position()?;
 
         unsafe {
             // directly mutate the memory slice under Header.
             let header_slice =
                 slice::from_raw_parts_mut(&mut header as *mut _ as *mut u8, header_size);
 
-            r.read_exact(header_slice).unwrap();
+            r.read_exact(header_slice)
+                .map_err(|_| ParserError::Truncated {
+                    section: "header",
+                    offset,
+                })?;
         }
 
-        header
+        Ok(header)
     }
 }
 
 impl FromReader for ParameterBlockHeader {
-    fn from_reader<R: Read + Seek>(r: &mut R) -> Self {
+    fn from_reader<R: Read + Seek>(r: &mut R) -> Result<Self, ParserError> {
         let mut parameter: ParameterBlockHeader = unsafe { mem::zeroed() };
+        let offset = r.stream_position()?;
 
         unsafe {
             let parameter_slice = slice::from_raw_parts_mut(&mut parameter as *mut _ as *mut u8, 4);
-            r.read_exact(parameter_slice).unwrap();
+            r.read_exact(parameter_slice)
+                .map_err(|_| ParserError::Truncated {
+                    section: "parameter header",
+                    offset,
+                })?;
         }
 
-        parameter
+        Ok(parameter)
     }
 }
 
 impl FromReader for ParameterBlock {
-    fn from_reader<R: Read + Seek>(r: &mut R) -> Self {
-        let header = ParameterBlockHeader::from_reader(r);
-
-        let mut u8_buffer = [0_u8];
-        let mut i8_buffer = [0_u8];
-        let mut i16_buffer = [0_u8; 2];
-        let mut string_buffer: Vec<u8> = vec![];
-        let mut parameter_buf: Vec<u8> = vec![];
-
-        parameter_buf.resize(header.parameter_block_counts as usize * 512 - 4, 0);
-        r.read_exact(&mut parameter_buf).unwrap();
+    fn from_reader<R: Read + Seek>(r: &mut R) -> Result<Self, ParserError> {
+        let header = ParameterBlockHeader::from_reader(r)?;
+        let processor = Processor::from_magic(header.magic_word).unwrap_or_default();
+
+        // records are located relative to the first byte after the parameter header.
+        let base = r.stream_position()?;
+        let mut parameter_buf =
+            vec![0_u8; (header.parameter_block_counts as usize * 512).saturating_sub(4)];
+        r.read_exact(&mut parameter_buf)
+            .map_err(|_| ParserError::Truncated {
+                section: "parameter block",
+                offset: base,
+            })?;
         let mut parameter_block_cursor = Cursor::new(&parameter_buf[..]);
 
         let mut groups = HashMap::<u8, GroupFormat>::new();
+        // parameters may come before their group, they are assigned once every record is read.
+        let mut params = vec![];
+        // records read so far, and where the bytes after the last one start.
+        let mut position = 0;
+        let mut tail_start;
 
         loop {
-            parameter_block_cursor.read_exact(&mut i8_buffer).unwrap();
-            let name_chars_size = i8::from_le_bytes(i8_buffer);
-
-            let locked = name_chars_size < 0;
-            let name_chars_size = name_chars_size.abs() as usize;
-
-            parameter_block_cursor.read_exact(&mut i8_buffer).unwrap();
-            let id: i8 = i8::from_le_bytes(i8_buffer);
+            let record_start = parameter_block_cursor.position();
+            tail_start = record_start;
+            let (record, next) = match read_record(&mut parameter_block_cursor, processor) {
+                Ok(Some(record)) => record,
+                Ok(None) => break,
+                Err(err) => {
+                    return Err(ParserError::InvalidParameter {
+                        offset: base + record_start,
+                        group: err.group,
+                        name: err.name,
+                        reason: err.reason,
+                    })
+                }
+            };
+
+            // the record along with any gap up to the next one, unless the offset points
+            // inside the record.
+            let record_end = parameter_block_cursor.position();
+            let raw_end = next.unwrap_or(record_end);
+            let raw = if raw_end >= record_end {
+                parameter_buf
+                    .get(record_start as usize..raw_end as usize)
+                    .map(<[u8]>::to_vec)
+            } else {
+                None
+            };
+
+            match record {
+                ParameterRecord::Parameter(mut param) => {
+                    param.position = Some(position);
+                    param.raw = raw;
+                    params.push(param);
+                }
+                ParameterRecord::Group {
+                    id,
+                    name,
+                    description,
+                    locked,
+                } => {
+                    let group = GroupFormat {
+                        id: id as i8,
+                        name,
+                        description,
+                        locked,
+                        params: HashMap::new(),
+                        position: Some(position),
+                        raw,
+                    };
+                    if let Some(old) = groups.insert(id, group) {
+                        log::warn!("group {} is declared twice, {:?} is dropped", id, old.name);
+                    }
+                }
+            }
+            position += 1;
 
-            if id == 0 || name_chars_size == 0 {
-                break;
+            match next {
+                Some(next) => parameter_block_cursor.set_position(next),
+                None => {
+                    tail_start = record_end;
+                    break;
+                }
             }
+        }
 
-            string_buffer.resize(name_chars_size, 0_u8);
-            parameter_block_cursor
-                .read_exact(&mut string_buffer)
-                .unwrap();
+        let mut orphans = vec![];
+        for param in params {
+            match groups.get_mut(&(param.id as u8)) {
+                Some(group) => {
+                    group.params.insert(param.name.clone(), param);
+                }
+                None => {
+                    log::warn!(
+                        "parameter {:?} refers to group {} which is not declared",
+                        param.name,
+                        param.id
+                    );
+                    orphans.push(param);
+                }
+            }
+        }
 
-            let name = String::from_utf8(string_buffer.clone()).unwrap();
+        // the group declared first takes the name, the others are kept to be written back.
+        let mut groups = groups.into_values().collect::<Vec<_>>();
+        groups.sort_by_key(|v| v.position);
+        let mut by_name = HashMap::<String, GroupFormat>::new();
+        let mut duplicates = vec![];
+        for group in groups {
+            if let Some(other) = by_name.get(&group.name) {
+                log::warn!(
+                    "groups {} and {} are both named {:?}, only the first is found by name",
+                    other.id,
+                    group.id,
+                    group.name
+                );
+                duplicates.push(group);
+                continue;
+            }
+            by_name.insert(group.name.clone(), group);
+        }
 
-            parameter_block_cursor.read_exact(&mut i16_buffer).unwrap();
-            let offset = i16::from_le_bytes(i16_buffer);
+        let tail = RawTail {
+            records: position,
+            bytes: parameter_buf
+                .get(tail_start as usize..)
+                .unwrap_or_default()
+                .to_vec(),
+        };
 
-            let is_param = id > 0;
-            if is_param {
-                // length of each data element
-                parameter_block_cursor.read_exact(&mut i8_buffer).unwrap();
-                let data_length = i8::from_le_bytes(i8_buffer);
+        Ok(ParameterBlock {
+            header,
+            groups: by_name,
+            duplicates,
+            orphans,
+            tail: Some(tail),
+        })
+    }
+}
 
-                // number of dimensions to read
-                parameter_block_cursor.read_exact(&mut u8_buffer).unwrap();
-                let num_dimensions = u8_buffer[0];
+enum ParameterRecord {
+    Group {
+        id: u8,
+        name: String,
+        description: String,
+        locked: bool,
+    },
+    Parameter(ParameterFormat),
+}
 
-                let mut num_elements = 1_i32;
-                let mut buf = [0_u8; 1];
+struct RecordError {
+    group: i8,
+    name: Option<String>,
+    reason: &'static str,
+}
 
-                let mut dimensions = vec![];
-                for _ in 0..num_dimensions {
-                    parameter_block_cursor.read_exact(&mut buf).unwrap();
-                    dimensions.push(buf[0]);
-                    num_elements *= buf[0] as i32;
-                }
+fn take<'a>(cursor: &mut Cursor<&'a [u8]>, n: usize) -> Option<&'a [u8]> {
+    let start = cursor.position() as usize;
+    let bytes = cursor.get_ref().get(start..start.checked_add(n)?)?;
+    cursor.set_position((start + n) as u64);
+    Some(bytes)
+}
 
-                let total_data_length = num_elements as i16 * data_length.abs() as i16;
-
-                let mut data_buffer = vec![0_u8; total_data_length as usize];
-                parameter_block_cursor.read_exact(&mut data_buffer).unwrap();
-
-                let datas: Vec<Box<dyn ParamValue>> = data_buffer
-                    .chunks_exact(data_length.abs() as usize)
-                    .filter_map(|arr| match data_length {
-                        1 => Some(Box::new(arr[0] as u8) as Box<dyn ParamValue>),
-                        2 => {
-                            let mut buf = [0_u8; 2];
-                            (arr.clone()).read_exact(&mut buf).unwrap();
-                            let val = i16::from_le_bytes(buf);
-                            Some(Box::new(val) as Box<dyn ParamValue>)
-                        }
-                        4 => {
-                            let mut buf = [0_u8; 4];
-                            (arr.clone()).read_exact(&mut buf).unwrap();
-                            let val = f32::from_le_bytes(buf);
-                            Some(Box::new(val))
-                        }
-                        -1 => Some(Box::new(arr[0] as char) as Box<dyn ParamValue>),
-                        _ => None,
-                    })
-                    .collect();
-                let param_data = ParamData { values: datas };
+/// names and descriptions should be ASCII, text that is not valid UTF-8 is taken as Latin-1
+/// as written by some European software.
+fn decode_text(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.iter().map(|v| *v as char).collect(),
+    }
+}
 
-                parameter_block_cursor.read_exact(&mut u8_buffer).unwrap();
-                let desc_chars_size = u8_buffer[0];
-                string_buffer.resize(desc_chars_size as usize, 0);
-                let desc = String::from_utf8(string_buffer.clone()).unwrap();
+/// the description closing every record, preceded by its length.
+fn read_description(cursor: &mut Cursor<&[u8]>) -> Option<String> {
+    let length = take(cursor, 1)?[0];
+    take(cursor, length as usize).map(decode_text)
+}
 
-                let param = ParameterFormat {
-                    id,
-                    name_chars_size: name_chars_size as u8,
-                    name: name.clone(),
-                    offset,
-                    data_length,
-                    num_dimensions,
-                    dimensions,
-                    parameter_data: param_data,
-                    desc_chars_size,
-                    description: desc,
-                    locked,
-                };
+/// read one group/parameter record, along with the position of the next record if the chain
+/// continues. `None` is returned once the terminating record is reached.
+#[allow(clippy::type_complexity)]
+fn read_record(
+    cursor: &mut Cursor<&[u8]>,
+    processor: Processor,
+) -> Result<Option<(ParameterRecord, Option<u64>)>, RecordError> {
+    let truncated = |group: i8, name: Option<&str>| RecordError {
+        group,
+        name: name.map(str::to_string),
+        reason: "record runs past the end of the parameter block",
+    };
+
+    let head = match take(cursor, 2) {
+        Some(head) => head,
+        // running out of records without a terminator is tolerated.
+        None => return Ok(None),
+    };
+    let name_chars_size = head[0] as i8;
+    let id = head[1] as i8;
+
+    let locked = name_chars_size < 0;
+    let name_chars_size = name_chars_size.unsigned_abs() as usize;
+
+    if id == 0 || name_chars_size == 0 {
+        return Ok(None);
+    }
 
-                let group_id = id as u8;
+    let name_bytes = take(cursor, name_chars_size).ok_or_else(|| truncated(id, None))?;
+    let name = decode_text(name_bytes);
 
-                if let Some(group) = groups.get_mut(&group_id) {
-                    group.params.insert(name, param);
-                } else {
-                    let mut group = GroupFormat::default();
-                    group.params.insert(name, param);
-                    groups.insert(group_id, group);
-                }
-            } else {
-                let group_id = id.abs() as u8;
-                parameter_block_cursor.read_exact(&mut u8_buffer).unwrap();
-                let desc_chars_size = u8_buffer[0];
-                string_buffer.resize(desc_chars_size as usize, 0);
-                let desc = String::from_utf8(string_buffer.clone()).unwrap();
-
-                if let Some(group) = groups.get_mut(&group_id) {
-                    group.name = name;
-                    group.description = desc;
-                } else {
-                    let new_group = GroupFormat {
-                        name,
-                        description: desc,
-                        locked,
-                        ..Default::default()
-                    };
-                    groups.insert(group_id, new_group);
-                }
-            }
+    let offset_at = cursor.position();
+    let offset = take(cursor, 2).ok_or_else(|| truncated(id, Some(&name)))?;
+    let offset = processor.read_i16([offset[0], offset[1]]);
 
-            parameter_buf = parameter_buf.split_off(2 + name_chars_size as usize + offset as usize);
-            parameter_block_cursor = Cursor::new(&parameter_buf[..]);
+    let next = match offset {
+        0 => None,
+        offset if offset < 0 => {
+            return Err(RecordError {
+                group: id,
+                name: Some(name),
+                reason: "offset to the next record points backwards",
+            })
         }
+        offset => Some(offset_at + offset as u64),
+    };
+
+    let is_param = id > 0;
+    let record = if is_param {
+        let mut u8_buffer = || take(cursor, 1).map(|v| v[0]);
+
+        // length of each data element
+        let data_length = u8_buffer().ok_or_else(|| truncated(id, Some(&name)))? as i8;
+
+        // number of dimensions to read
+        let num_dimensions = u8_buffer().ok_or_else(|| truncated(id, Some(&name)))?;
+
+        let dimensions = take(cursor, num_dimensions as usize)
+            .ok_or_else(|| truncated(id, Some(&name)))?
+            .to_vec();
+        // an overflowing size can't fit the block either.
+        let total_data_length = dimensions
+            .iter()
+            .try_fold(data_length.unsigned_abs() as usize, |acc, v| {
+                acc.checked_mul(*v as usize)
+            })
+            .ok_or_else(|| truncated(id, Some(&name)))?;
+        let data_buffer =
+            take(cursor, total_data_length).ok_or_else(|| truncated(id, Some(&name)))?;
+
+        let param_data = match data_length {
+            1 => ParamData::Byte(data_buffer.to_vec()),
+            2 => ParamData::Int16(
+                data_buffer
+                    .chunks_exact(2)
+                    .map(|arr| processor.read_i16([arr[0], arr[1]]))
+                    .collect(),
+            ),
+            4 => ParamData::Float32(
+                data_buffer
+                    .chunks_exact(4)
+                    .map(|arr| processor.read_f32([arr[0], arr[1], arr[2], arr[3]]))
+                    .collect(),
+            ),
+            -1 => ParamData::Char(data_buffer.iter().map(|v| *v as char).collect()),
+            // element types outside the specification are kept as they are.
+            _ => ParamData::Unknown {
+                data_length,
+                bytes: data_buffer.to_vec(),
+            },
+        };
 
-        let groups: HashMap<String, GroupFormat> = groups
-            .into_iter()
-            .map(|(_, v)| (v.name.clone(), v))
-            .collect();
-
-        let parameter_block = ParameterBlock { header, groups };
+        let desc = read_description(cursor).ok_or_else(|| truncated(id, Some(&name)))?;
+
+        ParameterRecord::Parameter(ParameterFormat {
+            id,
+            name,
+            data_length,
+            num_dimensions,
+            dimensions,
+            parameter_data: param_data,
+            description: desc,
+            locked,
+            position: None,
+            raw: None,
+        })
+    } else {
+        let desc = read_description(cursor).ok_or_else(|| truncated(id, Some(&name)))?;
+
+        ParameterRecord::Group {
+            id: id.unsigned_abs(),
+            name,
+            description: desc,
+            locked,
+        }
+    };
 
-        parameter_block
-    }
+    Ok(Some((record, next)))
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ParameterBlock {
     header: ParameterBlockHeader,
     pub groups: HashMap<String, GroupFormat>,
+    // groups named like one declared before them.
+    duplicates: Vec<GroupFormat>,
+    // parameters of groups that are never declared.
+    orphans: Vec<ParameterFormat>,
+    // bytes after the last record of a parsed section.
+    pub(crate) tail: Option<RawTail>,
+}
+
+/// What follows the chain of records in a parsed section, written back when every record is
+/// unchanged.
+#[derive(Debug, Clone)]
+pub(crate) struct RawTail {
+    // records in the parsed chain.
+    pub(crate) records: usize,
+    pub(crate) bytes: Vec<u8>,
+}
+
+impl Default for ParameterBlock {
+    fn default() -> Self {
+        ParameterBlock {
+            header: ParameterBlockHeader {
+                reserved_one: 1,
+                reserved_two: 0x50,
+                parameter_block_counts: 0,
+                magic_word: 0x50 + 4,
+            },
+            groups: HashMap::new(),
+            duplicates: vec![],
+            orphans: vec![],
+            tail: None,
+        }
+    }
+}
+
+/// id following the highest one in use, group ids are positive and stored in a byte.
+pub(crate) fn next_group_id(last: i8, group_name: &str) -> Result<i8, ParserError> {
+    last.checked_add(1)
+        .ok_or_else(|| ParserError::GroupIdsExhausted(group_name.to_string()))
 }
 
 impl ParameterBlock {
-    pub fn get(&self, key: &str) -> Option<&ParameterFormat> {
-        let split_key: &'static str;
+    /// an empty parameter section, used when composing a file from scratch.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// processor type the values were decoded with, Intel if the magic word is not recognized.
+    pub fn processor(&self) -> Processor {
+        Processor::from_magic(self.header.magic_word).unwrap_or_default()
+    }
 
-        if key.contains(".") {
-            split_key = ".";
-        } else if key.contains(":") {
-            split_key = ":";
+    /// processor type the values are encoded with when written back.
+    pub fn set_processor(&mut self, processor: Processor) {
+        self.header.magic_word = processor.magic();
+    }
+
+    /// insert a parameter into the group, creating the group if it is not yet present.
+    ///
+    /// Fails when a new group is needed but every positive group id is taken.
+    pub fn insert(
+        &mut self,
+        group_name: &str,
+        mut param: ParameterFormat,
+    ) -> Result<(), ParserError> {
+        let next_id = if self.groups.contains_key(group_name) {
+            0
         } else {
-            return None;
+            next_group_id(self.last_group_id(), group_name)?
+        };
+
+        let group = self
+            .groups
+            .entry(group_name.to_string())
+            .or_insert_with(|| GroupFormat {
+                id: next_id,
+                name: group_name.to_string(),
+                ..Default::default()
+            });
+
+        param.id = group.id;
+        // a replaced parameter keeps its place in the section.
+        if let Some(old) = group.params.get(&param.name) {
+            param.position = old.position;
         }
+        group.params.insert(param.name.clone(), param);
+        Ok(())
+    }
+
+    /// highest group id in use, by a group or an orphan parameter.
+    pub(crate) fn last_group_id(&self) -> i8 {
+        let groups = self.groups.values().chain(&self.duplicates).map(|v| v.id);
+        let orphans = self.orphans.iter().map(|v| v.id);
+        groups.chain(orphans).max().unwrap_or(0).max(0)
+    }
+
+    pub fn group_by_id(&self, id: i8) -> Option<&GroupFormat> {
+        // i8::MIN has no positive counterpart.
+        self.groups
+            .values()
+            .chain(&self.duplicates)
+            .find(|v| v.id.unsigned_abs() == id.unsigned_abs())
+    }
+
+    /// parameters whose group id matches no group record, they are written back unchanged.
+    pub fn orphans(&self) -> &[ParameterFormat] {
+        &self.orphans
+    }
+
+    /// groups sharing their name with a group declared earlier, only reachable by id. They
+    /// are written back unchanged.
+    pub fn duplicate_groups(&self) -> &[GroupFormat] {
+        &self.duplicates
+    }
+
+    /// groups in the order they were declared, groups added afterwards come last.
+    pub fn groups_in_order(&self) -> Vec<&GroupFormat> {
+        let mut groups = self
+            .groups
+            .values()
+            .chain(&self.duplicates)
+            .collect::<Vec<_>>();
+        groups.sort_by_key(|v| (v.position.unwrap_or(usize::MAX), v.id));
+        groups
+    }
+
+    /// the map keys of the group and parameter named by `key`, ignoring case and padding.
+    fn resolve<K: ParameterKey>(&self, key: &K) -> Option<(&str, &str)> {
+        let (group_key, param_key) = key.parts()?;
 
-        let mut iter = key.split(split_key);
-        let group_key = iter.next().unwrap();
-        let param_key = iter.next().unwrap();
+        let group = match self.groups.get_key_value(group_key) {
+            Some(group) => group,
+            None => {
+                let group_key = normalize(group_key);
+                self.groups
+                    .iter()
+                    .find(|(name, _)| normalize(name) == group_key)?
+            }
+        };
 
-        if let Some(group) = self.groups.get(group_key) {
-            if let Some(param) = group.params.get(param_key) {
-                return Some(&param);
+        let param = match group.1.params.get_key_value(param_key) {
+            Some(param) => param,
+            None => {
+                let param_key = normalize(param_key);
+                group
+                    .1
+                    .params
+                    .iter()
+                    .find(|(name, _)| normalize(name) == param_key)?
+            }
+        };
+
+        Some((group.0.as_str(), param.0.as_str()))
+    }
+
+    /// `GROUP:PARAM`, `GROUP.PARAM` or `(group, param)`, names are matched ignoring case and
+    /// surrounding whitespace.
+    pub fn get<K: ParameterKey>(&self, key: K) -> Option<&ParameterFormat> {
+        let (group_key, param_key) = self.resolve(&key)?;
+        self.groups.get(group_key)?.params.get(param_key)
+    }
+
+    pub fn get_mut<K: ParameterKey>(&mut self, key: K) -> Option<&mut ParameterFormat> {
+        let (group_key, param_key) = self.resolve(&key)?;
+        let (group_key, param_key) = (group_key.to_string(), param_key.to_string());
+        self.groups.get_mut(&group_key)?.params.get_mut(&param_key)
+    }
+
+    /// take out the group and its parameters, the name is matched as in `get`.
+    pub fn remove_group(&mut self, name: &str) -> Option<GroupFormat> {
+        let key = if self.groups.contains_key(name) {
+            name.to_string()
+        } else {
+            let name = normalize(name);
+            self.groups.keys().find(|v| normalize(v) == name)?.clone()
+        };
+        self.groups.remove(&key)
+    }
+
+    /// the parameter converted to `T`, e.g. `f32` for its first value or `Vec<String>`.
+    pub fn get_typed<T: FromParameter>(&self, key: impl ParameterKey) -> Result<T, ParserError> {
+        let param = self
+            .get(&key)
+            .ok_or_else(|| ParserError::MissingParameter(key.name()))?;
+
+        T::from_parameter(param).ok_or_else(|| ParserError::WrongParameterType {
+            key: key.name(),
+            expected: T::EXPECTED,
+            found: param.parameter_data.type_name(),
+        })
+    }
+}
+
+/// names of groups and parameters are case-insensitive, some vendors pad them.
+fn normalize(name: &str) -> String {
+    name.trim_matches(|c: char| c.is_whitespace() || c == '\0')
+        .to_uppercase()
+}
+
+/// Ways of naming a parameter: `"GROUP:PARAM"`, `"GROUP.PARAM"` or `("GROUP", "PARAM")`.
+pub trait ParameterKey {
+    /// group and parameter name, None if the key can't be split.
+    fn parts(&self) -> Option<(&str, &str)>;
+
+    /// the key as given, for error messages.
+    fn name(&self) -> String;
+}
+
+impl ParameterKey for &str {
+    fn parts(&self) -> Option<(&str, &str)> {
+        self.split_once([':', '.'])
+    }
+
+    fn name(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl ParameterKey for String {
+    fn parts(&self) -> Option<(&str, &str)> {
+        self.split_once([':', '.'])
+    }
+
+    fn name(&self) -> String {
+        self.clone()
+    }
+}
+
+impl ParameterKey for (&str, &str) {
+    fn parts(&self) -> Option<(&str, &str)> {
+        Some((self.0, self.1))
+    }
+
+    fn name(&self) -> String {
+        format!("{}:{}", self.0, self.1)
+    }
+}
+
+impl<K: ParameterKey> ParameterKey for &K {
+    fn parts(&self) -> Option<(&str, &str)> {
+        (*self).parts()
+    }
+
+    fn name(&self) -> String {
+        (*self).name()
+    }
+}
+
+/// Types a parameter can be read as through `ParameterBlock::get_typed`.
+pub trait FromParameter: Sized {
+    /// the type as shown in errors.
+    const EXPECTED: &'static str;
+
+    fn from_parameter(param: &ParameterFormat) -> Option<Self>;
+}
+
+macro_rules! from_parameter {
+    ($ty:ty, $name:expr) => {
+        impl FromParameter for $ty {
+            const EXPECTED: &'static str = $name;
+
+            fn from_parameter(param: &ParameterFormat) -> Option<Self> {
+                param.get_scalar::<$ty>()
+            }
+        }
+
+        impl FromParameter for Vec<$ty> {
+            const EXPECTED: &'static str = concat!("a list of ", $name);
+
+            fn from_parameter(param: &ParameterFormat) -> Option<Self> {
+                param.values::<$ty>().map(<[$ty]>::to_vec)
             }
         }
+    };
+}
+
+from_parameter!(char, "char");
+from_parameter!(u8, "byte");
+from_parameter!(i16, "i16");
+from_parameter!(f32, "f32");
+
+impl FromParameter for String {
+    const EXPECTED: &'static str = "string";
+
+    fn from_parameter(param: &ParameterFormat) -> Option<Self> {
+        param.as_strings()?.into_iter().next()
+    }
+}
 
-        None
+impl FromParameter for Vec<String> {
+    const EXPECTED: &'static str = "a list of string";
+
+    fn from_parameter(param: &ParameterFormat) -> Option<Self> {
+        param.as_strings()
     }
 }
 
@@ -605,104 +1506,353 @@ struct ParameterBlockHeader {
     magic_word: u8,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ParameterFormat {
-    // indicates "locked" if value is negative.
-    name_chars_size: u8,
+    // id of the group this parameter belongs to.
     id: i8,
     pub name: String,
-    offset: i16,
     pub data_length: i8,
     pub num_dimensions: u8,
     pub dimensions: Vec<u8>,
     pub parameter_data: ParamData,
-    desc_chars_size: u8,
     pub description: String,
     pub locked: bool,
+    // index of the record in the parsed section.
+    pub(crate) position: Option<usize>,
+    // bytes the record was parsed from, reused by the writer while the values match.
+    pub(crate) raw: Option<Vec<u8>>,
 }
 
-#[derive(Default, Debug)]
+impl ParameterFormat {
+    /// the element type is taken from the data, dimensions are in file order (fastest first).
+    pub fn new(name: &str, dimensions: Vec<u8>, data: ParamData) -> Self {
+        ParameterFormat {
+            id: 0,
+            name: name.to_string(),
+            data_length: data.data_length(),
+            num_dimensions: dimensions.len() as u8,
+            dimensions,
+            parameter_data: data,
+            description: String::new(),
+            locked: false,
+            position: None,
+            raw: None,
+        }
+    }
+
+    /// id of the group the parameter belongs to, 0 until it is inserted into a group.
+    pub fn group_id(&self) -> i8 {
+        self.id
+    }
+
+    /// a scalar parameter without dimensions.
+    pub fn scalar<T: ParamType>(name: &str, value: T) -> Self {
+        Self::new(name, vec![], T::wrap(vec![value]))
+    }
+
+    /// a char array holding `values` padded to the longest one. Dimensions are single bytes,
+    /// so only the first 255 values are kept and each is cut after 255 characters.
+    pub fn strings(name: &str, values: &[&str]) -> Self {
+        let values = &values[..values.len().min(u8::MAX as usize)];
+        let width = values
+            .iter()
+            .map(|v| v.chars().count())
+            .max()
+            .unwrap_or(0)
+            .min(u8::MAX as usize);
+        let chars = values
+            .iter()
+            .flat_map(|v| v.chars().chain(std::iter::repeat(' ')).take(width))
+            .collect::<Vec<char>>();
+        let dimensions = if values.len() == 1 {
+            vec![width as u8]
+        } else {
+            vec![width as u8, values.len() as u8]
+        };
+        Self::new(name, dimensions, ParamData::Char(chars))
+    }
+
+    /// all values of the parameter if they are of type `T`.
+    pub fn values<T: ParamType>(&self) -> Option<&[T]> {
+        T::unwrap(&self.parameter_data)
+    }
+
+    /// the first value of the parameter if it is of type `T`.
+    pub fn get_scalar<T: ParamType>(&self) -> Option<T> {
+        self.values::<T>()?.first().copied()
+    }
+
+    /// char arrays split along the first dimension, with the padding trimmed.
+    pub fn as_strings(&self) -> Option<Vec<String>> {
+        let chars = self.values::<char>()?;
+        let row = self.dimensions.first().copied().unwrap_or(1).max(1) as usize;
+
+        Some(
+            chars
+                .chunks(row)
+                .map(|arr| arr.iter().collect::<String>().trim().to_string())
+                .collect(),
+        )
+    }
+
+    pub fn as_i16_vec(&self) -> Option<Vec<i16>> {
+        self.values::<i16>().map(<[i16]>::to_vec)
+    }
+
+    pub fn as_f32_vec(&self) -> Option<Vec<f32>> {
+        self.values::<f32>().map(<[f32]>::to_vec)
+    }
+
+    /// floats split along the first dimension, e.g. `FORCE_PLATFORM:CORNERS` (3x4xN) yields
+    /// 4N rows of xyz.
+    pub fn as_f32_matrix(&self) -> Option<Vec<Vec<f32>>> {
+        let floats = self.values::<f32>()?;
+        let row = self.dimensions.first().copied().unwrap_or(1).max(1) as usize;
+
+        Some(floats.chunks(row).map(<[f32]>::to_vec).collect())
+    }
+}
+
+#[derive(Default, Debug, Clone)]
 pub struct GroupFormat {
+    /// positive group number shared by its parameters, 0 lets the writer pick one.
+    pub id: i8,
     pub name: String,
     pub description: String,
     pub locked: bool,
     pub params: HashMap<String, ParameterFormat>,
+    pub(crate) position: Option<usize>,
+    pub(crate) raw: Option<Vec<u8>>,
 }
 
-#[derive(Debug)]
-pub struct ParamData {
-    pub values: Vec<Box<dyn ParamValue>>,
+impl GroupFormat {
+    /// parameters in the order they were declared, parameters added afterwards come last.
+    pub fn params_in_order(&self) -> Vec<&ParameterFormat> {
+        let mut params = self.params.values().collect::<Vec<_>>();
+        params.sort_by(|a, b| {
+            (a.position.unwrap_or(usize::MAX), &a.name)
+                .cmp(&(b.position.unwrap_or(usize::MAX), &b.name))
+        });
+        params
+    }
 }
 
-#[derive(Debug)]
-pub struct PointData {
-    pub values: Vec<[f32; 5]>,
+/// Values of a parameter, flattened in file order (first dimension varies fastest).
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamData {
+    Char(Vec<char>),
+    Byte(Vec<u8>),
+    Int16(Vec<i16>),
+    Float32(Vec<f32>),
+    /// elements of a type outside the specification, left undecoded.
+    Unknown {
+        data_length: i8,
+        bytes: Vec<u8>,
+    },
 }
 
-#[derive(Debug)]
-pub struct AnalogData {
-    pub values: Vec<f32>,
-}
+impl ParamData {
+    /// -1 for chars, 1 for bytes, 2 for i16 and 4 for f32, as stored in the file.
+    pub fn data_length(&self) -> i8 {
+        match self {
+            ParamData::Char(_) => -1,
+            ParamData::Byte(_) => 1,
+            ParamData::Int16(_) => 2,
+            ParamData::Float32(_) => 4,
+            ParamData::Unknown { data_length, .. } => *data_length,
+        }
+    }
 
-pub trait ParamValue: std::fmt::Debug + ParamClone {
-    fn as_char(&self) -> Option<&char> {
-        None
+    /// element type as shown in errors.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ParamData::Char(_) => "char",
+            ParamData::Byte(_) => "byte",
+            ParamData::Int16(_) => "i16",
+            ParamData::Float32(_) => "f32",
+            ParamData::Unknown { .. } => "undecoded",
+        }
     }
-    fn as_f32(&self) -> Option<&f32> {
-        None
+
+    pub fn len(&self) -> usize {
+        match self {
+            ParamData::Char(v) => v.len(),
+            ParamData::Byte(v) => v.len(),
+            ParamData::Int16(v) => v.len(),
+            ParamData::Float32(v) => v.len(),
+            ParamData::Unknown { data_length, bytes } => {
+                bytes.len() / data_length.unsigned_abs().max(1) as usize
+            }
+        }
     }
-    fn as_i16(&self) -> Option<&i16> {
-        None
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
-    fn as_u8(&self) -> Option<&u8> {
-        None
+}
+
+/// Element types a parameter can hold.
+pub trait ParamType: Copy + Sized {
+    fn wrap(values: Vec<Self>) -> ParamData;
+    fn unwrap(data: &ParamData) -> Option<&[Self]>;
+}
+
+impl ParamType for char {
+    fn wrap(values: Vec<Self>) -> ParamData {
+        ParamData::Char(values)
+    }
+    fn unwrap(data: &ParamData) -> Option<&[Self]> {
+        match data {
+            ParamData::Char(v) => Some(v),
+            _ => None,
+        }
     }
 }
 
-pub trait ParamClone {
-    fn clone_box(&self) -> Box<dyn ParamValue>;
+impl ParamType for u8 {
+    fn wrap(values: Vec<Self>) -> ParamData {
+        ParamData::Byte(values)
+    }
+    fn unwrap(data: &ParamData) -> Option<&[Self]> {
+        match data {
+            ParamData::Byte(v) => Some(v),
+            _ => None,
+        }
+    }
 }
 
-impl<T> ParamClone for T
-where
-    T: 'static + ParamValue + Clone,
-{
-    fn clone_box(&self) -> Box<dyn ParamValue> {
-        Box::new(self.clone())
+impl ParamType for i16 {
+    fn wrap(values: Vec<Self>) -> ParamData {
+        ParamData::Int16(values)
+    }
+    fn unwrap(data: &ParamData) -> Option<&[Self]> {
+        match data {
+            ParamData::Int16(v) => Some(v),
+            _ => None,
+        }
     }
 }
 
-impl ParamValue for char {
-    fn as_char(&self) -> Option<&char> {
-        Some(self)
+impl ParamType for f32 {
+    fn wrap(values: Vec<Self>) -> ParamData {
+        ParamData::Float32(values)
+    }
+    fn unwrap(data: &ParamData) -> Option<&[Self]> {
+        match data {
+            ParamData::Float32(v) => Some(v),
+            _ => None,
+        }
     }
 }
-impl ParamValue for f32 {
-    fn as_f32(&self) -> Option<&f32> {
-        Some(self)
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PointData {
+    pub values: Vec<Point>,
+}
+
+/// A marker position in one frame.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    /// None if the point was not reconstructed in this frame.
+    pub residual: Option<f32>,
+    pub cameras: CameraMask,
+}
+
+impl Point {
+    pub fn is_valid(&self) -> bool {
+        self.residual.is_some()
+    }
+
+    pub fn position(&self) -> [f32; 3] {
+        [self.x, self.y, self.z]
     }
 }
 
-impl ParamValue for i16 {
-    fn as_i16(&self) -> Option<&i16> {
-        Some(&self)
+/// Cameras that observed a point, bit `n` is set if camera `n` saw it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CameraMask(pub u8);
+
+impl CameraMask {
+    /// number of cameras that observed the point.
+    pub fn count(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    pub fn contains(self, camera: u8) -> bool {
+        camera < 8 && self.0 & (1 << camera) != 0
     }
 }
 
-impl ParamValue for u8 {
-    fn as_u8(&self) -> Option<&u8> {
-        Some(&self)
+/// Analog samples recorded during one point frame, as a channels x subframes matrix.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnalogData {
+    pub channels: usize,
+    pub subframes: usize,
+    /// channel major, the subframes of channel `c` are `values[c * subframes..(c + 1) * subframes]`.
+    pub values: Vec<f32>,
+}
+
+impl AnalogData {
+    /// all subframes of a channel.
+    pub fn channel(&self, channel: usize) -> Option<&[f32]> {
+        if channel >= self.channels {
+            return None;
+        }
+        self.values
+            .get(channel * self.subframes..(channel + 1) * self.subframes)
+    }
+
+    pub fn get(&self, channel: usize, subframe: usize) -> Option<f32> {
+        if subframe >= self.subframes {
+            return None;
+        }
+        self.channel(channel).map(|v| v[subframe])
+    }
+
+    /// every channel's value at the subframe.
+    pub fn subframe(&self, subframe: usize) -> Option<Vec<f32>> {
+        (0..self.channels)
+            .map(|channel| self.get(channel, subframe))
+            .collect()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs::File;
 
     fn set_logger() {
         femme::with_level(log::LevelFilter::Debug);
     }
 
+    /// write a file from scratch, POINT:DATA_START is added for the writer to fill in.
+    pub(crate) fn synthetic_bytes(
+        header: HeaderBlock,
+        mut parameter: ParameterBlock,
+        frames: impl IntoIterator<Item = (PointData, Option<AnalogData>)>,
+    ) -> Result<Vec<u8>> {
+        if parameter.get("POINT:DATA_START").is_none() {
+            parameter.insert("POINT", ParameterFormat::scalar("DATA_START", 0_i16))?;
+        }
+        let mut writer = C3dWriter::new(Cursor::new(vec![]), header, parameter)?;
+        writer.write_frames(frames)?;
+        Ok(writer.finish()?.into_inner())
+    }
+
+    /// `synthetic_bytes` opened again by an adapter.
+    pub(crate) fn synthetic(
+        header: HeaderBlock,
+        parameter: ParameterBlock,
+        frames: impl IntoIterator<Item = (PointData, Option<AnalogData>)>,
+    ) -> Result<C3dAdapter<Cursor<Vec<u8>>>> {
+        let bytes = synthetic_bytes(header, parameter, frames)?;
+        Ok(C3dAdapter::new(Cursor::new(bytes))?.construct()?)
+    }
+
     #[test]
     fn test_parser() -> Result<()> {
         set_logger();
@@ -715,19 +1865,673 @@ mod tests {
         let mut cursor = Cursor::new(&buf[..]);
 
         let adapter = C3dAdapter::new(&mut cursor)?.construct()?;
-        for (i, p, a) in adapter.reader()?.into_iter() {
+        for (i, p, a) in adapter.reader()? {
             dbg!(i, p, a);
         }
 
         adapter.get_point_labels().unwrap();
-        adapter.get_analog_labels().unwrap();
+        let analog_labels = adapter.get_analog_labels().unwrap();
+
+        // the label getters drop inner whitespace, `as_strings` only trims the padding.
+        assert!(analog_labels.contains(&"Raw.Pin1".to_string()));
+        let parameter = adapter.parameter.as_ref().unwrap();
+        let labels = parameter
+            .get("ANALOG:LABELS")
+            .unwrap()
+            .as_strings()
+            .unwrap();
+        assert!(labels.contains(&"Raw.Pin 1".to_string()));
+
+        let (_, _, analog) = adapter.reader()?.next().unwrap();
+        let analog = analog.unwrap();
+        assert_eq!((analog.channels, analog.subframes), (22, 4));
+        assert_eq!(analog.channel(6).unwrap().len(), 4);
+        assert_eq!(analog.get(6, 0), Some(-1.467_437_7));
+
+        // motion_shadow.c3d is not shipped with the repository.
+        if let Ok(mut file) = File::open("test_data/motion_shadow.c3d") {
+            let adapter = C3dAdapter::new(&mut file)?.construct()?;
+            for (i, p, a) in adapter.reader()? {
+                dbg!(i, p, a);
+            }
+        }
 
-        let mut file = File::open("test_data/motion_shadow.c3d")?;
-        let adapter = C3dAdapter::new(&mut file)?.construct()?;
-        for (i, p, a) in adapter.reader()?.into_iter() {
-            dbg!(i, p, a);
+        Ok(())
+    }
+    #[test]
+    fn test_descriptions() -> Result<()> {
+        let file = File::open("test_data/vicon_trial.c3d")?;
+        let adapter = C3dAdapter::new(file)?.construct()?;
+        let parameter = adapter.parameter.as_ref().unwrap();
+        // vicon leaves every description empty.
+        assert_eq!(parameter.groups["POINT"].description, "");
+        assert_eq!(parameter.get("POINT:LABELS").unwrap().description, "");
+        assert!(parameter
+            .groups
+            .values()
+            .flat_map(|v| v.params.values())
+            .all(|v| v.description.is_empty()));
+
+        // a Latin-1 group description followed by a UTF-8 parameter description.
+        let mut block = vec![1, 0x50, 1, 84];
+        block.extend_from_slice(&[5, (-1_i8) as u8]);
+        block.extend_from_slice(b"POINT");
+        block.extend_from_slice(&10_i16.to_le_bytes());
+        block.extend_from_slice(&[7, b'D', b'o', b'n', b'n', 0xe9, b'e', b's']);
+        block.extend_from_slice(&[6, 1]);
+        block.extend_from_slice(b"LABELS");
+        let description = "Marker names, entrée".as_bytes();
+        let offset = 2 + 1 + 2 + 4 + 1 + description.len();
+        block.extend_from_slice(&(offset as i16).to_le_bytes());
+        block.extend_from_slice(&[(-1_i8) as u8, 2, 4, 1]);
+        block.extend_from_slice(b"LASI");
+        block.push(description.len() as u8);
+        block.extend_from_slice(description);
+        block.resize(512, 0);
+
+        let parameter = ParameterBlock::from_reader(&mut Cursor::new(&block[..]))?;
+        assert_eq!(parameter.groups["POINT"].description, "Données");
+        let labels = parameter.get("POINT:LABELS").unwrap();
+        assert_eq!(labels.description, "Marker names, entrée");
+        assert_eq!(labels.as_strings(), Some(vec!["LASI".to_string()]));
+
+        // descriptions survive the writer.
+        let mut parameter = parameter;
+        parameter.insert("POINT", ParameterFormat::scalar("DATA_START", 0_i16))?;
+        let writer = C3dWriter::new(Cursor::new(vec![]), HeaderBlock::default(), parameter)?;
+        let written = writer.finish()?.into_inner();
+        let adapter = C3dAdapter::new(Cursor::new(&written[..]))?.construct()?;
+        let parameter = adapter.parameter.as_ref().unwrap();
+        assert_eq!(parameter.groups["POINT"].description, "Données");
+        assert_eq!(
+            parameter.get("POINT:LABELS").unwrap().description,
+            "Marker names, entrée"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_group_resolution() -> Result<()> {
+        // a parameter ahead of its locked group, as written by Cortex, then an orphan.
+        let mut block = vec![1, 0x50, 1, 84];
+        block.extend_from_slice(&[6, 1]);
+        block.extend_from_slice(b"LABELS");
+        block.extend_from_slice(&10_i16.to_le_bytes());
+        block.extend_from_slice(&[(-1_i8) as u8, 1, 4]);
+        block.extend_from_slice(b"LASI");
+        block.push(0);
+        block.extend_from_slice(&[(-5_i8) as u8, (-1_i8) as u8]);
+        block.extend_from_slice(b"POINT");
+        block.extend_from_slice(&3_i16.to_le_bytes());
+        block.push(0);
+        block.extend_from_slice(&[4, 7]);
+        block.extend_from_slice(b"GAIN");
+        block.extend_from_slice(&0_i16.to_le_bytes());
+        block.extend_from_slice(&[4, 0]);
+        block.extend_from_slice(&2.5_f32.to_le_bytes());
+        block.push(0);
+        block.resize(512, 0);
+
+        let mut file = vec![0_u8; 512];
+        file[..2].copy_from_slice(&[2, 0x50]);
+        file[16..18].copy_from_slice(&3_u16.to_le_bytes());
+        file.extend_from_slice(&block);
+
+        let adapter = C3dAdapter::new(Cursor::new(&file[..]))?.construct()?;
+        let parameter = adapter.parameter.clone().unwrap();
+        assert_eq!(parameter.groups.len(), 1);
+        let point = parameter.group_by_id(1).unwrap();
+        assert_eq!((point.name.as_str(), point.locked), ("POINT", true));
+        assert_eq!(adapter.get_point_labels(), Some(vec!["LASI".to_string()]));
+        assert!(parameter.group_by_id(7).is_none());
+
+        let orphans = parameter.orphans();
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(
+            (orphans[0].name.as_str(), orphans[0].group_id()),
+            ("GAIN", 7)
+        );
+
+        // records are written back in their original order, orphans included.
+        let header = *adapter.header.as_ref().unwrap();
+        let writer = C3dWriter::new(Cursor::new(vec![]), header, parameter.clone())?;
+        let written = writer.finish()?.into_inner();
+        assert_eq!(&written[512..1024], &block[..]);
+
+        // a new group does not take the id of the orphan.
+        let mut parameter = parameter;
+        parameter.insert("ANALOG", ParameterFormat::scalar("USED", 0_i16))?;
+        assert_eq!(parameter.groups["ANALOG"].id, 8);
+
+        // a corrupt group id of -128.
+        let mut block = vec![1, 0x50, 1, 84];
+        block.extend_from_slice(&[5, i8::MIN as u8]);
+        block.extend_from_slice(b"POINT");
+        block.extend_from_slice(&0_i16.to_le_bytes());
+        block.push(0);
+        block.resize(512, 0);
+        let parameter = ParameterBlock::from_reader(&mut Cursor::new(&block[..]))?;
+        assert_eq!(parameter.group_by_id(i8::MIN).unwrap().name, "POINT");
+        assert!(parameter.group_by_id(1).is_none());
+
+        // two groups of the same name, the first one is found by name.
+        let mut block = vec![1, 0x50, 1, 84];
+        for id in 1..=2_i8 {
+            block.extend_from_slice(&[5, (-id) as u8]);
+            block.extend_from_slice(b"POINT");
+            block.extend_from_slice(&3_i16.to_le_bytes());
+            block.push(0);
+            block.extend_from_slice(&[4, id as u8]);
+            block.extend_from_slice(b"USED");
+            let next = if id == 2 { 0_i16 } else { 7 };
+            block.extend_from_slice(&next.to_le_bytes());
+            block.extend_from_slice(&[2, 0]);
+            block.extend_from_slice(&(id as i16 * 10).to_le_bytes());
+            block.push(0);
+        }
+        block.resize(512, 0);
+
+        let mut file = vec![0_u8; 512];
+        file[..2].copy_from_slice(&[2, 0x50]);
+        file[16..18].copy_from_slice(&3_u16.to_le_bytes());
+        file.extend_from_slice(&block);
+
+        let adapter = C3dAdapter::new(Cursor::new(&file[..]))?.construct()?;
+        let mut parameter = adapter.parameter.clone().unwrap();
+        assert_eq!(parameter.get_typed::<i16>("POINT:USED")?, 10);
+        let duplicate = parameter.group_by_id(2).unwrap();
+        assert_eq!(duplicate.params["USED"].get_scalar(), Some(20_i16));
+        assert_eq!(parameter.duplicate_groups().len(), 1);
+
+        // the duplicate is written back, also once the section is rebuilt.
+        let header = *adapter.header.as_ref().unwrap();
+        let writer = C3dWriter::new(Cursor::new(vec![]), header, parameter.clone())?;
+        let written = writer.finish()?.into_inner();
+        assert_eq!(&written[512..1024], &block[..]);
+
+        parameter.insert("ANALOG", ParameterFormat::scalar("USED", 0_i16))?;
+        assert_eq!(parameter.groups["ANALOG"].id, 3);
+        let writer = C3dWriter::new(Cursor::new(vec![]), header, parameter)?;
+        let written = writer.finish()?.into_inner();
+        let read = C3dAdapter::new(Cursor::new(&written[..]))?.construct()?;
+        let read = read.parameter.as_ref().unwrap();
+        assert_eq!(read.get_typed::<i16>("POINT:USED")?, 10);
+        assert_eq!(
+            read.group_by_id(2).unwrap().params["USED"].get_scalar(),
+            Some(20_i16)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parameter_lookup() -> Result<()> {
+        let file = File::open("test_data/vicon_trial.c3d")?;
+        let adapter = C3dAdapter::new(file)?.construct()?;
+        let parameter = adapter.parameter.as_ref().unwrap();
+
+        let rate = parameter.get("POINT:RATE").unwrap();
+        for key in ["point:rate", "Point.Rate", " POINT : rate "].iter() {
+            assert!(std::ptr::eq(parameter.get(*key).unwrap(), rate));
+        }
+        assert!(std::ptr::eq(
+            parameter.get(("point", "RATE")).unwrap(),
+            rate
+        ));
+        assert!(parameter.get("POINT").is_none());
+
+        assert_eq!(parameter.get_typed::<f32>("point:rate")?, 250.0);
+        assert_eq!(parameter.get_typed::<String>("POINT:UNITS")?, "mm");
+        assert_eq!(
+            parameter.get_typed::<Vec<i16>>(("force_platform", "channel"))?[..6],
+            [1, 2, 3, 4, 5, 6]
+        );
+
+        let err = parameter.get_typed::<i16>("POINT:RATE").unwrap_err();
+        assert!(matches!(
+            &err,
+            ParserError::WrongParameterType {
+                expected: "i16",
+                found: "f32",
+                ..
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "parameter POINT:RATE holds f32 values, expected i16"
+        );
+        assert!(matches!(
+            parameter.get_typed::<f32>("POINT:SPEED"),
+            Err(ParserError::MissingParameter(key)) if key == "POINT:SPEED"
+        ));
+
+        // names padded by the vendor.
+        let mut parameter = ParameterBlock::new();
+        parameter.insert("Vendor ", ParameterFormat::scalar("Gain\0", 2_i16))?;
+        assert_eq!(parameter.get_typed::<i16>("VENDOR:GAIN")?, 2);
+        parameter.get_mut("vendor.gain").unwrap().parameter_data = ParamData::Int16(vec![3]);
+        assert_eq!(parameter.get_typed::<i16>(("vendor", "gain"))?, 3);
+
+        // once group 127 exists only the groups already present take new parameters.
+        parameter.insert("LAST", ParameterFormat::scalar("USED", 1_i16))?;
+        parameter.groups.get_mut("LAST").unwrap().id = i8::MAX;
+        parameter.insert("LAST", ParameterFormat::scalar("USED", 2_i16))?;
+        parameter.insert("Vendor ", ParameterFormat::scalar("USED", 1_i16))?;
+        assert!(matches!(
+            parameter.insert("EXTRA", ParameterFormat::scalar("USED", 1_i16)),
+            Err(ParserError::GroupIdsExhausted(name)) if name == "EXTRA"
+        ));
+        assert!(parameter.get("EXTRA:USED").is_none());
+
+        // a group added without an id gets one from the writer, which fails the same way.
+        parameter.groups.insert(
+            "EXTRA".to_string(),
+            GroupFormat {
+                name: "EXTRA".to_string(),
+                ..Default::default()
+            },
+        );
+        assert!(matches!(
+            C3dWriter::new(Cursor::new(vec![]), HeaderBlock::default(), parameter),
+            Err(ParserError::GroupIdsExhausted(name)) if name == "EXTRA"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_processor_types() -> Result<()> {
+        let mut buf: Vec<u8> = vec![];
+        File::open("test_data/vicon_trial.c3d")?.read_to_end(&mut buf)?;
+
+        let adapter = C3dAdapter::new(Cursor::new(&buf[..]))?.construct()?;
+        let header = *adapter.header.as_ref().unwrap();
+        let expected = adapter.reader()?.collect::<Vec<_>>();
+        assert_eq!(
+            adapter.parameter.as_ref().unwrap().processor(),
+            Processor::Intel
+        );
+
+        for processor in [Processor::Dec, Processor::Mips].iter() {
+            let mut parameter = adapter.parameter.clone().unwrap();
+            parameter.set_processor(*processor);
+
+            let mut writer = C3dWriter::new(Cursor::new(vec![]), header, parameter)?;
+            writer.write_frames(expected.iter().map(|(_, p, a)| (p.clone(), a.clone())))?;
+            let written = writer.finish()?.into_inner();
+            assert_eq!(written[512 + 3], processor.magic());
+
+            let converted = C3dAdapter::new(Cursor::new(&written[..]))?.construct()?;
+            let converted_header = converted.header.as_ref().unwrap();
+            let converted_parameter = converted.parameter.as_ref().unwrap();
+            assert_eq!(converted_parameter.processor(), *processor);
+            assert_eq!({ converted_header.point_counts }, { header.point_counts });
+            assert_eq!({ converted_header.frame_rate }, { header.frame_rate });
+            assert_eq!(converted.get_point_labels(), adapter.get_point_labels());
+            assert_eq!(
+                converted_parameter
+                    .get("POINT:RATE")
+                    .and_then(|p| p.get_scalar::<f32>()),
+                Some(250.0)
+            );
+
+            for ((i, p, a), (ei, ep, ea)) in converted.reader()?.zip(expected.iter()) {
+                assert_eq!(i, *ei);
+                assert_eq!(p.values, ep.values);
+                assert_eq!(a.unwrap().values, ea.as_ref().unwrap().values);
+            }
+        }
+
+        Ok(())
+    }
+    #[test]
+    fn test_corrupted_input() -> Result<()> {
+        let mut buf: Vec<u8> = vec![];
+        File::open("test_data/vicon_trial.c3d")?.read_to_end(&mut buf)?;
+
+        // xorshift, deterministic so failures can be replayed.
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut rand = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        // the header, parameter block and the first frames are where the layout is decided.
+        let interesting = 512 * 16;
+
+        for case in 0..400 {
+            let mut corrupted = buf.clone();
+            if case % 2 == 0 {
+                corrupted.truncate(rand() as usize % interesting);
+            } else {
+                for _ in 0..1 + rand() % 8 {
+                    let at = rand() as usize % interesting;
+                    corrupted[at] = rand() as u8;
+                }
+            }
+
+            let adapter = match C3dAdapter::new(Cursor::new(&corrupted[..]))?.construct() {
+                Ok(adapter) => adapter,
+                Err(_) => continue,
+            };
+            adapter.get_point_labels();
+            adapter.get_analog_labels();
+            adapter.events();
+            adapter.event_set();
+            if let Ok(reader) = adapter.reader() {
+                reader.take(32).for_each(drop);
+            };
+            // the truncated copies are cheap to load whole.
+            if case % 2 == 0 {
+                let _ = adapter.trial();
+            }
+        }
+
+        // a header event far past the end of the trial.
+        let mut header = HeaderBlock {
+            frame_rate: 100.0,
+            event_counts: 2,
+            ..Default::default()
+        };
+        header.event_times[0] = f32::INFINITY;
+        header.event_times[1] = 1e30;
+        let adapter = synthetic(header, ParameterBlock::new(), vec![])?;
+        let events = adapter.events().unwrap();
+        assert_eq!((events[0].frame, events[1].frame), (u32::MAX, u32::MAX));
+        assert!(adapter.event_set().is_some());
+        adapter.trial()?;
+
+        // a float POINT:FRAMES that does not fit after the first frame.
+        let header = HeaderBlock {
+            frame_first: 2,
+            frame_last: 5,
+            ..Default::default()
+        };
+        let mut parameter = ParameterBlock::new();
+        parameter.insert("POINT", ParameterFormat::scalar("FRAMES", u32::MAX as f32))?;
+        let adapter = synthetic(header, parameter, vec![])?;
+        assert_eq!(adapter.frames(), Some(2..=5));
+
+        let truncated = C3dAdapter::new(Cursor::new(&buf[..1000]))?.construct();
+        assert!(matches!(
+            truncated,
+            Err(ParserError::Truncated {
+                section: "parameter block",
+                offset: 516
+            })
+        ));
+
+        Ok(())
+    }
+    #[test]
+    fn test_typed_parameters() -> Result<()> {
+        let adapter = C3dAdapter::new(File::open("test_data/vicon_trial.c3d")?)?.construct()?;
+        let parameter = adapter.parameter.as_ref().unwrap();
+
+        let labels = parameter.get("POINT:LABELS").unwrap().as_strings().unwrap();
+        assert_eq!(labels.len(), 10);
+        assert_eq!(labels[0], "basketball1");
+
+        let corners = parameter.get("FORCE_PLATFORM:CORNERS").unwrap();
+        let corners = corners.as_f32_matrix().unwrap();
+        assert_eq!(corners.len(), 4);
+        assert_eq!(corners[1], vec![498.92, 498.92, 0.0]);
+
+        let channels = parameter.get("FORCE_PLATFORM:CHANNEL").unwrap();
+        assert_eq!(channels.as_i16_vec().unwrap(), vec![1, 2, 3, 4, 5, 6]);
+
+        let rate = parameter.get("POINT:RATE").unwrap();
+        assert_eq!(rate.get_scalar::<f32>(), Some(250.0));
+        assert_eq!(rate.get_scalar::<i16>(), None);
+        assert_eq!(
+            parameter.get("MANUFACTURER:COMPANY").unwrap().as_strings(),
+            Some(vec!["Vicon".to_string()])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_analog_format() -> Result<()> {
+        let header = HeaderBlock {
+            analog_counts: 2,
+            frame_first: 1,
+            frame_last: 1,
+            scale: 1.0,
+            analog_per_frame: 2,
+            frame_rate: 100.0,
+            ..Default::default()
+        };
+
+        // (format, offset, bits, expected raw words)
+        let cases = vec![
+            ("SIGNED", Some(0_i16), None, [-5_i16 as u16, 7]),
+            (" unsigned ", Some(-32768_i16), None, [32763, 32775]),
+            ("UNSIGNED", None, Some(12_i16), [2043, 2055]),
+            ("BOGUS", Some(0_i16), None, [-5_i16 as u16, 7]),
+        ];
+
+        for (format, offset, bits, expected) in cases {
+            let mut parameter = ParameterBlock::new();
+            parameter.insert("ANALOG", ParameterFormat::scalar("USED", 1_i16))?;
+            parameter.insert("ANALOG", ParameterFormat::strings("FORMAT", &[format]))?;
+            if let Some(offset) = offset {
+                parameter.insert("ANALOG", ParameterFormat::scalar("OFFSET", offset))?;
+            }
+            if let Some(bits) = bits {
+                parameter.insert("ANALOG", ParameterFormat::scalar("BITS", bits))?;
+            }
+
+            let analog = AnalogData {
+                channels: 1,
+                subframes: 2,
+                values: vec![-5.0, 7.0],
+            };
+            let frame = (PointData { values: vec![] }, Some(analog.clone()));
+            let written = synthetic_bytes(header, parameter, vec![frame])?;
+
+            let data_start = (written[16] as usize - 1) * 512;
+            let raw = [
+                u16::from_le_bytes([written[data_start], written[data_start + 1]]),
+                u16::from_le_bytes([written[data_start + 2], written[data_start + 3]]),
+            ];
+            assert_eq!(raw, expected, "format {:?}", format);
+
+            let adapter = C3dAdapter::new(Cursor::new(&written[..]))?.construct()?;
+            let (_, _, read) = adapter.reader()?.next().unwrap();
+            assert_eq!(read.unwrap().values, analog.values, "format {:?}", format);
         }
 
         Ok(())
     }
+
+    #[test]
+    fn test_long_capture() -> Result<()> {
+        let header = HeaderBlock {
+            point_counts: 1,
+            frame_first: 1,
+            frame_last: u16::MAX,
+            scale: -1.0,
+            frame_rate: 200.0,
+            ..Default::default()
+        };
+
+        let mut parameter = ParameterBlock::new();
+        parameter.insert(
+            "TRIAL",
+            ParameterFormat::new("ACTUAL_START_FIELD", vec![2], ParamData::Int16(vec![1, 0])),
+        )?;
+        parameter.insert(
+            "TRIAL",
+            ParameterFormat::new(
+                "ACTUAL_END_FIELD",
+                vec![2],
+                ParamData::Int16(vec![(70_000 - 65_536) as i16, 1]),
+            ),
+        )?;
+
+        let frames = (0..70_000).map(|i| {
+            let points = PointData {
+                values: vec![Point {
+                    x: i as f32,
+                    residual: Some(0.0),
+                    ..Default::default()
+                }],
+            };
+            (points, None)
+        });
+        let adapter = synthetic(header, parameter, frames)?;
+        assert_eq!(adapter.frames(), Some(1..=70_000));
+
+        let mut count = 0;
+        for (idx, points, _) in adapter.reader()? {
+            assert_eq!(points.values[0].x, (idx - 1) as f32);
+            count += 1;
+        }
+        assert_eq!(count, 70_000);
+
+        // without the trial fields a float POINT:FRAMES takes over.
+        let mut parameter = ParameterBlock::new();
+        parameter.insert("POINT", ParameterFormat::scalar("FRAMES", 100_000.0_f32))?;
+        assert_eq!(frame_range(&header, &parameter), 1..=100_000);
+
+        let file = File::open("test_data/vicon_trial.c3d")?;
+        let adapter = C3dAdapter::new(file)?.construct()?;
+        assert_eq!(adapter.frames(), Some(1..=2129));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_point_residual() -> Result<()> {
+        // the eighth camera only fits in float files, integer words must stay positive.
+        for (scale, cameras) in [(-0.1_f32, 0b1000_0011_u8), (0.1, 0b0000_0011)].iter() {
+            let header = HeaderBlock {
+                point_counts: 2,
+                frame_first: 1,
+                frame_last: 1,
+                scale: *scale,
+                frame_rate: 100.0,
+                ..Default::default()
+            };
+            let point = Point {
+                x: 1.0,
+                y: 2.0,
+                z: 3.0,
+                residual: Some(0.2),
+                cameras: CameraMask(0b1000_0011),
+            };
+            let points = PointData {
+                values: vec![point, Point::default()],
+            };
+
+            let adapter = synthetic(header, ParameterBlock::new(), vec![(points, None)])?;
+            let (_, read, _) = adapter.reader()?.next().unwrap();
+            let (valid, invalid) = (read.values[0], read.values[1]);
+
+            assert!((valid.residual.unwrap() - 0.2).abs() < 1e-4);
+            assert_eq!(valid.cameras, CameraMask(*cameras));
+            assert_eq!(valid.cameras.count(), cameras.count_ones());
+            assert!(valid.cameras.contains(1) && !valid.cameras.contains(2));
+            assert_eq!(invalid.residual, None);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reader_ownership() -> Result<()> {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<C3dAdapter<File>>();
+        fn assert_send<T: Send>() {}
+        assert_send::<C3dReader<'static, File>>();
+
+        let file = File::open("test_data/vicon_trial.c3d")?;
+        let adapter = C3dAdapter::new(file)?.construct()?;
+
+        let mut first = adapter.reader()?;
+        assert!(matches!(adapter.reader(), Err(ParserError::ReaderInUse)));
+        let (idx, _, _) = first.next().unwrap();
+        assert_eq!(idx, 1);
+
+        // dropping the reader hands the file back, the next one starts over.
+        drop(first);
+        assert_eq!(adapter.reader()?.count(), 2129);
+
+        let counts = std::thread::spawn(move || adapter.reader().map(|v| v.count()))
+            .join()
+            .unwrap()?;
+        assert_eq!(counts, 2129);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_random_access() -> Result<()> {
+        let file = File::open("test_data/vicon_trial.c3d")?;
+        let adapter = C3dAdapter::new(file)?.construct()?;
+        let frames = adapter.reader()?.collect::<Vec<_>>();
+
+        assert_eq!(adapter.read_frame(1000)?, frames[999]);
+        assert_eq!(adapter.read_frame(2129)?, frames[2128]);
+        assert!(matches!(
+            adapter.read_frame(2130),
+            Err(ParserError::FrameOutOfRange(2130))
+        ));
+        assert!(adapter.read_frame(0).is_err());
+
+        let range = adapter.read_range(100..200)?;
+        assert_eq!(range.len(), 100);
+        assert_eq!(range.collect::<Vec<_>>(), frames[99..199].to_vec());
+        assert_eq!(adapter.read_range(2000..)?.len(), 130);
+        assert_eq!(adapter.read_range(..=3)?.len(), 3);
+        assert!(adapter.read_range(2000..2200).is_err());
+
+        let mut reversed = adapter.reader()?.rev().collect::<Vec<_>>();
+        reversed.reverse();
+        assert_eq!(reversed, frames);
+
+        // both ends meet in the middle without reading a frame twice.
+        let mut reader = adapter.reader()?;
+        reader.seek_frame(2127)?;
+        assert_eq!(reader.len(), 3);
+        assert_eq!(reader.next_back().unwrap().0, 2129);
+        assert_eq!(reader.next().unwrap().0, 2127);
+        assert_eq!(reader.next_back().unwrap().0, 2128);
+        assert!(reader.next().is_none());
+
+        // a cut off last frame is not counted.
+        let mut buf: Vec<u8> = vec![];
+        File::open("test_data/vicon_trial.c3d")?.read_to_end(&mut buf)?;
+        let adapter = C3dAdapter::new(Cursor::new(&buf[..buf.len() - 100]))?.construct()?;
+        let reader = adapter.reader()?;
+        assert_eq!(reader.len(), 2128);
+        assert_eq!(reader.count(), 2128);
+        assert_eq!(adapter.reader()?.next_back().unwrap().0, 2128);
+        let range = adapter.read_range(2000..)?;
+        assert_eq!(range.len(), 129);
+        assert_eq!(range.count(), 129);
+        assert!(adapter.read_frame(2129).is_err());
+
+        // a trial without frames reads as empty.
+        let header = HeaderBlock {
+            point_counts: 1,
+            frame_first: 1,
+            frame_last: 0,
+            scale: -1.0,
+            frame_rate: 100.0,
+            ..Default::default()
+        };
+        let adapter = synthetic(header, ParameterBlock::new(), vec![])?;
+        assert_eq!(adapter.reader()?.len(), 0);
+        assert!(adapter.reader()?.next_back().is_none());
+        assert_eq!(adapter.trial()?.frames, 0);
+        #[cfg(feature = "rayon")]
+        assert_eq!(adapter.read_all_parallel()?.frames, 0);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,106 @@
+/// Processor type recorded in the fourth byte of the parameter section, it decides the byte
+/// order of integers and the representation of floats across the whole file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Processor {
+    /// little endian integers, IEEE floats.
+    #[default]
+    Intel,
+    /// little endian integers, VAX-F floats.
+    Dec,
+    /// big endian integers and IEEE floats (SGI/MIPS).
+    Mips,
+}
+
+impl Processor {
+    /// 83 + processor type, i.e. 84 for Intel, 85 for DEC and 86 for MIPS.
+    pub fn from_magic(magic_word: u8) -> Option<Self> {
+        match magic_word {
+            84 => Some(Processor::Intel),
+            85 => Some(Processor::Dec),
+            86 => Some(Processor::Mips),
+            _ => None,
+        }
+    }
+
+    pub fn magic(self) -> u8 {
+        match self {
+            Processor::Intel => 84,
+            Processor::Dec => 85,
+            Processor::Mips => 86,
+        }
+    }
+
+    pub fn read_u16(self, bytes: [u8; 2]) -> u16 {
+        match self {
+            Processor::Mips => u16::from_be_bytes(bytes),
+            _ => u16::from_le_bytes(bytes),
+        }
+    }
+
+    pub fn read_i16(self, bytes: [u8; 2]) -> i16 {
+        self.read_u16(bytes) as i16
+    }
+
+    pub fn read_f32(self, bytes: [u8; 4]) -> f32 {
+        match self {
+            Processor::Intel => f32::from_le_bytes(bytes),
+            Processor::Mips => f32::from_be_bytes(bytes),
+            Processor::Dec => {
+                // VAX-F keeps the 16 bit words in swapped order, the exponent is biased by 128
+                // instead of 127 and the hidden bit sits right of the binary point.
+                if bytes[1] & 0x7f == 0 && bytes[0] & 0x80 == 0 {
+                    return 0.0;
+                }
+                f32::from_le_bytes([bytes[2], bytes[3], bytes[0], bytes[1]]) / 4.0
+            }
+        }
+    }
+
+    pub fn write_u16(self, value: u16) -> [u8; 2] {
+        match self {
+            Processor::Mips => value.to_be_bytes(),
+            _ => value.to_le_bytes(),
+        }
+    }
+
+    pub fn write_i16(self, value: i16) -> [u8; 2] {
+        self.write_u16(value as u16)
+    }
+
+    pub fn write_f32(self, value: f32) -> [u8; 4] {
+        match self {
+            Processor::Intel => value.to_le_bytes(),
+            Processor::Mips => value.to_be_bytes(),
+            Processor::Dec => {
+                if value == 0.0 {
+                    return [0; 4];
+                }
+                let bytes = (value * 4.0).to_le_bytes();
+                [bytes[2], bytes[3], bytes[0], bytes[1]]
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_values() {
+        assert_eq!(Processor::Dec.read_f32([0x80, 0x40, 0x00, 0x00]), 1.0);
+        assert_eq!(Processor::Dec.read_f32([0x80, 0xc0, 0x00, 0x00]), -1.0);
+        assert_eq!(Processor::Dec.read_f32([0x00, 0x00, 0x00, 0x00]), 0.0);
+        assert_eq!(Processor::Mips.read_f32([0x3f, 0x80, 0x00, 0x00]), 1.0);
+        assert_eq!(Processor::Mips.read_i16([0xff, 0xfe]), -2);
+        assert_eq!(Processor::Dec.read_i16([0xfe, 0xff]), -2);
+
+        for processor in [Processor::Intel, Processor::Dec, Processor::Mips].iter() {
+            for value in [1.0_f32, -0.01, 250.0, 12345.678, -3.5e-3].iter() {
+                let bytes = processor.write_f32(*value);
+                assert_eq!(processor.read_f32(bytes), *value);
+            }
+            assert_eq!(processor.read_i16(processor.write_i16(-12345)), -12345);
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use crate::frame::Projection;
+use crate::trial::find_label;
+use crate::{C3dAdapter, C3dReader, ParserError};
+use std::io::{Read, Seek};
+
+enum Channel {
+    Label(String),
+    Index(usize),
+}
+
+/// Builds a reader decoding only some markers and analog channels, the frames it yields
+/// hold them in the order they were added.
+pub struct ProjectionBuilder<'a, T: Read + Seek> {
+    adapter: &'a C3dAdapter<T>,
+    markers: Vec<String>,
+    channels: Vec<Channel>,
+}
+
+impl<'a, T: Read + Seek> ProjectionBuilder<'a, T> {
+    pub(crate) fn new(adapter: &'a C3dAdapter<T>) -> Self {
+        ProjectionBuilder {
+            adapter,
+            markers: vec![],
+            channels: vec![],
+        }
+    }
+
+    /// a marker from POINT:LABELS, `Subject:LASI` also matches `LASI`.
+    pub fn marker(mut self, label: &str) -> Self {
+        self.markers.push(label.to_string());
+        self
+    }
+
+    pub fn markers<'l>(mut self, labels: impl IntoIterator<Item = &'l str>) -> Self {
+        self.markers
+            .extend(labels.into_iter().map(|v| v.to_string()));
+        self
+    }
+
+    /// an analog channel from ANALOG:LABELS, `Force.Fz1` also matches `Fz1`.
+    pub fn channel(mut self, label: &str) -> Self {
+        self.channels.push(Channel::Label(label.to_string()));
+        self
+    }
+
+    /// an analog channel by its position, counted from 0.
+    pub fn channel_index(mut self, index: usize) -> Self {
+        self.channels.push(Channel::Index(index));
+        self
+    }
+
+    pub fn build(self) -> Result<C3dReader<'a, T>, ParserError> {
+        let mut reader = self.adapter.reader()?;
+        let point_counts = reader.decoder.point_counts();
+        let (channel_counts, _) = reader.decoder.analog_layout();
+
+        let point_labels = self.adapter.get_point_labels().unwrap_or_default();
+        let markers = self
+            .markers
+            .into_iter()
+            .map(|label| {
+                find_label(&point_labels, &label)
+                    .filter(|v| *v < point_counts)
+                    .ok_or(ParserError::UnknownLabel(label))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let analog_labels = self.adapter.get_analog_labels().unwrap_or_default();
+        let channels = self
+            .channels
+            .into_iter()
+            .map(|channel| match channel {
+                Channel::Label(label) => find_label(&analog_labels, &label)
+                    .filter(|v| *v < channel_counts)
+                    .ok_or(ParserError::UnknownLabel(label)),
+                Channel::Index(index) if index < channel_counts => Ok(index),
+                Channel::Index(index) => Err(ParserError::ChannelOutOfRange(index)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        reader.project(Projection { markers, channels });
+        Ok(reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::{self, BufReader, SeekFrom};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_projection() -> anyhow::Result<()> {
+        let file = BufReader::new(File::open("test_data/vicon_trial.c3d")?);
+        let adapter = C3dAdapter::new(file)?.construct()?;
+        let frames = adapter.reader()?.collect::<Vec<_>>();
+
+        let projected = adapter
+            .projection()
+            .markers(vec!["basketball3", "basketball1"])
+            .channel("Fz1")
+            .channel_index(0)
+            .build()?;
+        assert_eq!(projected.len(), frames.len());
+
+        for ((idx, points, analog), (expected_idx, expected, expected_analog)) in
+            projected.zip(frames.iter())
+        {
+            assert_eq!(idx, *expected_idx);
+            assert_eq!(points.values, vec![expected.values[2], expected.values[0]]);
+
+            let analog = analog.unwrap();
+            let expected_analog = expected_analog.as_ref().unwrap();
+            assert_eq!((analog.channels, analog.subframes), (2, 4));
+            assert_eq!(analog.channel(0), expected_analog.channel(2));
+            assert_eq!(analog.channel(1), expected_analog.channel(0));
+        }
+
+        // only the analog channels, read backwards.
+        let mut projected = adapter.projection().channel("Force.Fx1").build()?.rev();
+        let (idx, points, analog) = projected.next().unwrap();
+        assert_eq!(idx, 2129);
+        assert!(points.values.is_empty());
+        assert_eq!(
+            analog.unwrap().channel(0),
+            frames[2128].2.as_ref().unwrap().channel(0)
+        );
+        drop(projected);
+
+        assert!(matches!(
+            adapter.projection().marker("LASI").build(),
+            Err(ParserError::UnknownLabel(label)) if label == "LASI"
+        ));
+        assert!(matches!(
+            adapter.projection().channel_index(22).build(),
+            Err(ParserError::ChannelOutOfRange(22))
+        ));
+        // the handle is back after a failed build.
+        assert!(adapter.reader().is_ok());
+
+        Ok(())
+    }
+
+    /// counts the seeks made on the inner reader.
+    struct Seeks<R> {
+        inner: R,
+        count: Arc<AtomicUsize>,
+    }
+
+    impl<R: Read> Read for Seeks<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.inner.read(buf)
+        }
+    }
+
+    impl<R: Seek> Seek for Seeks<R> {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.count.fetch_add(1, Ordering::SeqCst);
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn test_projection_reads_through() -> anyhow::Result<()> {
+        let count = Arc::new(AtomicUsize::new(0));
+        let file = Seeks {
+            inner: BufReader::new(File::open("test_data/vicon_trial.c3d")?),
+            count: count.clone(),
+        };
+        let adapter = C3dAdapter::new(file)?.construct()?;
+
+        let projected = adapter.projection().marker("basketball2").build()?;
+        let seeks = count.load(Ordering::SeqCst);
+        assert_eq!(projected.count(), 2129);
+        // one seek to the span of the first frame, the gaps after it are read.
+        assert_eq!(count.load(Ordering::SeqCst), seeks + 1);
+
+        Ok(())
+    }
+}
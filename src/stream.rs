@@ -0,0 +1,206 @@
+use crate::frame::FrameDecoder;
+use crate::{
+    frame_range, validate, AnalogData, Baseline, FromReader, HeaderBlock, ParameterBlock,
+    ParserError, PointData,
+};
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Counts the bytes read from a plain `Read`, seeking forward skips bytes and seeking
+/// backwards fails.
+struct Sequential<R> {
+    inner: R,
+    position: u64,
+}
+
+impl<R: Read> Sequential<R> {
+    /// skip up to `offset`, the start of `section`.
+    fn skip_to(&mut self, offset: u64, section: &'static str) -> Result<(), ParserError> {
+        if offset < self.position {
+            return Err(ParserError::BackwardSeek {
+                section,
+                offset,
+                position: self.position,
+            });
+        }
+
+        let length = offset - self.position;
+        if self.skip(length)? < length {
+            return Err(ParserError::Truncated {
+                section,
+                offset: self.position,
+            });
+        }
+        Ok(())
+    }
+
+    /// bytes actually skipped, less than `length` at the end of the source.
+    fn skip(&mut self, length: u64) -> io::Result<u64> {
+        io::copy(&mut self.by_ref().take(length), &mut io::sink())
+    }
+}
+
+impl<R: Read> Read for Sequential<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: Read> Seek for Sequential<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(v) => Some(v),
+            SeekFrom::Current(v) if v >= 0 => Some(self.position + v as u64),
+            _ => None,
+        };
+
+        match target {
+            Some(target) if target >= self.position => {
+                self.skip(target - self.position)?;
+                Ok(self.position)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "cannot seek backwards in a stream",
+            )),
+        }
+    }
+}
+
+/// Reads a c3d file front to back from a source that cannot seek, e.g. stdin or an http
+/// body. Header, parameters and padding are consumed as they come, then frames are read in
+/// order.
+pub struct C3dStream<R: Read> {
+    pub header: HeaderBlock,
+    pub parameter: ParameterBlock,
+    source: Sequential<R>,
+    buffer: Vec<u8>,
+    decoder: FrameDecoder,
+    // next frame to read and the end of the trial, exclusive.
+    front: u64,
+    back: u64,
+}
+
+impl<R: Read> C3dStream<R> {
+    /// read up to the first frame, fails if a section starts before the bytes already read.
+    pub fn new(source: R) -> Result<Self, ParserError> {
+        let mut source = Sequential {
+            inner: source,
+            position: 0,
+        };
+
+        let header = HeaderBlock::from_reader(&mut source)?;
+        let parameter_offset = (header.parameter_start as u64).saturating_sub(1) * 512;
+        source.skip_to(parameter_offset, "parameter block")?;
+        let parameter = ParameterBlock::from_reader(&mut source)?;
+        let header = validate(header, &parameter)?;
+
+        let data_offset = (header.data_start as u64 - 1) * 512;
+        source.skip_to(data_offset, "data section")?;
+
+        let frames = frame_range(&header, &parameter);
+        Ok(C3dStream {
+            decoder: FrameDecoder::new(&header, &parameter),
+            front: *frames.start() as u64,
+            back: *frames.end() as u64 + 1,
+            header,
+            parameter,
+            source,
+            buffer: vec![],
+        })
+    }
+
+    /// subtract the force plate baseline from every analog frame read afterwards.
+    pub fn with_baseline(mut self, baseline: Baseline) -> Self {
+        self.decoder.baseline.replace(baseline);
+        self
+    }
+
+    /// the source, positioned after the last frame read.
+    pub fn into_inner(self) -> R {
+        self.source.inner
+    }
+}
+
+impl<R: Read> Iterator for C3dStream<R> {
+    type Item = (u32, PointData, Option<AnalogData>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
+
+        self.buffer.resize(self.decoder.frame_length(), 0_u8);
+        // a truncated data section ends the iteration.
+        if self.source.read_exact(&mut self.buffer[..]).is_err() {
+            self.front = self.back;
+            return None;
+        }
+
+        let frame = self.front;
+        self.front += 1;
+        let (points, analog) = self.decoder.decode(&self.buffer);
+        Some((frame as u32, points, analog))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // the stream may end early.
+        (0, Some(self.back.saturating_sub(self.front) as usize))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::C3dAdapter;
+    use std::fs::File;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_stream() -> anyhow::Result<()> {
+        let mut bytes = vec![];
+        File::open("test_data/vicon_trial.c3d")?.read_to_end(&mut bytes)?;
+
+        let adapter = C3dAdapter::new(Cursor::new(&bytes[..]))?.construct()?;
+        let frames = adapter.reader()?.collect::<Vec<_>>();
+
+        // a slice is `Read` but not `Seek`, read it through a small buffer like a pipe.
+        let stream = C3dStream::new(io::BufReader::with_capacity(7, &bytes[..]))?;
+        assert_eq!(
+            stream.parameter.get("POINT:LABELS").unwrap().as_strings(),
+            adapter.get_point_labels()
+        );
+        assert_eq!(stream.collect::<Vec<_>>(), frames);
+
+        // the stream stops at a truncated frame.
+        let stream = C3dStream::new(&bytes[..bytes.len() - 100])?;
+        assert_eq!(stream.count(), frames.len() - 1);
+
+        // data section pointing back into the parameter block, data_start is word 9.
+        let mut corrupted = bytes.clone();
+        corrupted[16..18].copy_from_slice(&2_u16.to_le_bytes());
+        assert!(matches!(
+            C3dStream::new(&corrupted[..]),
+            Err(ParserError::BackwardSeek {
+                section: "data section",
+                offset: 512,
+                ..
+            })
+        ));
+
+        // parameter block pointing into the header.
+        let mut corrupted = bytes.clone();
+        corrupted[0] = 1;
+        assert!(matches!(
+            C3dStream::new(&corrupted[..]),
+            Err(ParserError::BackwardSeek {
+                section: "parameter block",
+                offset: 0,
+                position: 512,
+            })
+        ));
+
+        Ok(())
+    }
+}
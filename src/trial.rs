@@ -0,0 +1,410 @@
+use crate::{AnalogData, C3dAdapter, CameraMask, EventSet, ParserError, PointData};
+use std::io::{Read, Seek};
+
+/// A whole trial loaded into contiguous columnar arrays.
+#[derive(Debug, Clone, Default)]
+pub struct Trial {
+    /// index of the first frame, usually 1.
+    pub first_frame: u32,
+    pub frames: usize,
+    pub markers: usize,
+    pub point_rate: f32,
+    pub point_labels: Vec<String>,
+    pub point_units: String,
+    /// markers x frames positions, marker major.
+    pub points: Vec<[f32; 3]>,
+    /// markers x frames, NaN where the point was not reconstructed.
+    pub residuals: Vec<f32>,
+    /// markers x frames.
+    pub cameras: Vec<CameraMask>,
+    pub analog_rate: f32,
+    pub analog_labels: Vec<String>,
+    pub analog_units: Vec<String>,
+    pub channels: usize,
+    /// analog samples per channel, frames x subframes.
+    pub samples: usize,
+    /// channels x samples, channel major.
+    pub analog: Vec<f32>,
+    pub events: EventSet,
+}
+
+impl Trial {
+    /// read every frame of the adapter.
+    pub fn from_adapter<T: Read + Seek>(adapter: &C3dAdapter<T>) -> Result<Self, ParserError> {
+        let mut trial = Trial::describe(adapter)?;
+        for (_, points, analog) in adapter.reader()? {
+            trial.push(&points, analog.as_ref());
+        }
+        trial.finish();
+        Ok(trial)
+    }
+
+    /// read the data section at once and decode chunks of frames in parallel.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn from_adapter_parallel<T: Read + Seek>(
+        adapter: &C3dAdapter<T>,
+    ) -> Result<Self, ParserError> {
+        use rayon::prelude::*;
+
+        // frames handed to a single task.
+        const CHUNK: usize = 1024;
+
+        let mut trial = Trial::describe(adapter)?;
+        let mut reader = adapter.reader()?;
+        let frames = reader.len();
+        let bytes = reader.read_remaining()?;
+        let decoder = &reader.decoder;
+
+        let frame_length = decoder.frame_length();
+        // as with the sequential reader, a truncated frame ends the trial.
+        let frames = bytes.len().checked_div(frame_length).unwrap_or(frames);
+        let markers = trial.markers;
+        let (channels, subframes) = decoder.analog_layout();
+        let block = channels * subframes;
+
+        // frame major until transposed below.
+        let mut points = vec![[0.0; 3]; frames * markers];
+        let mut residuals = vec![0.0; frames * markers];
+        let mut cameras = vec![CameraMask::default(); frames * markers];
+        let mut analog = vec![0.0; frames * block];
+
+        // driven by the chunk index, a file without markers or channels leaves the
+        // matching buffers empty and every chunk of them too.
+        let chunks = match frame_length {
+            0 => 0,
+            _ => frames.div_ceil(CHUNK),
+        };
+        split_chunks(&mut points, CHUNK * markers, chunks)
+            .into_par_iter()
+            .zip(split_chunks(&mut residuals, CHUNK * markers, chunks))
+            .zip(split_chunks(&mut cameras, CHUNK * markers, chunks))
+            .zip(split_chunks(&mut analog, CHUNK * block, chunks))
+            .enumerate()
+            .for_each(|(chunk, (((points, residuals), cameras), analog))| {
+                let start = chunk * CHUNK * frame_length;
+                let end = (start + CHUNK * frame_length).min(frames * frame_length);
+                for (idx, frame) in bytes[start..end].chunks_exact(frame_length).enumerate() {
+                    let (point_data, analog_data) = decoder.decode(frame);
+                    for (marker, point) in point_data.values.iter().enumerate() {
+                        let at = idx * markers + marker;
+                        points[at] = point.position();
+                        residuals[at] = point.residual.unwrap_or(f32::NAN);
+                        cameras[at] = point.cameras;
+                    }
+                    if let Some(analog_data) = analog_data {
+                        analog[idx * block..(idx + 1) * block].copy_from_slice(&analog_data.values);
+                    }
+                }
+            });
+
+        trial.frames = frames;
+        trial.points = transpose_parallel(&points, markers, 1);
+        trial.residuals = transpose_parallel(&residuals, markers, 1);
+        trial.cameras = transpose_parallel(&cameras, markers, 1);
+        if block > 0 && frames > 0 {
+            trial.channels = channels;
+            trial.samples = frames * subframes;
+            trial.analog = transpose_parallel(&analog, channels, subframes);
+        }
+
+        Ok(trial)
+    }
+
+    /// an empty trial with the labels, rates, units and events of the adapter.
+    pub(crate) fn describe<T: Read + Seek>(adapter: &C3dAdapter<T>) -> Result<Self, ParserError> {
+        let header = adapter.header.as_ref().ok_or(ParserError::MissingField)?;
+        let parameter = adapter
+            .parameter
+            .as_ref()
+            .ok_or(ParserError::MissingField)?;
+
+        let point_rate = parameter
+            .get("POINT:RATE")
+            .and_then(|v| v.get_scalar::<f32>())
+            .unwrap_or(header.frame_rate);
+        let analog_rate = parameter
+            .get("ANALOG:RATE")
+            .and_then(|v| v.get_scalar::<f32>())
+            .unwrap_or(header.frame_rate * header.analog_per_frame as f32);
+        let strings = |key: &str| {
+            parameter
+                .get(key)
+                .and_then(|v| v.as_strings())
+                .unwrap_or_default()
+        };
+
+        Ok(Trial {
+            first_frame: adapter.frames().map(|v| *v.start()).unwrap_or(1),
+            markers: header.point_counts as usize,
+            point_rate,
+            point_labels: adapter.get_point_labels().unwrap_or_default(),
+            point_units: strings("POINT:UNITS")
+                .into_iter()
+                .next()
+                .unwrap_or_default(),
+            analog_rate,
+            analog_labels: adapter.get_analog_labels().unwrap_or_default(),
+            analog_units: strings("ANALOG:UNITS"),
+            events: adapter.event_set().unwrap_or_default(),
+            ..Default::default()
+        })
+    }
+
+    /// append a frame, values are kept frame major until `finish`.
+    pub(crate) fn push(&mut self, points: &PointData, analog: Option<&AnalogData>) {
+        self.frames += 1;
+        for point in points.values.iter() {
+            self.points.push(point.position());
+            self.residuals.push(point.residual.unwrap_or(f32::NAN));
+            self.cameras.push(point.cameras);
+        }
+
+        if let Some(analog) = analog {
+            self.channels = analog.channels;
+            self.samples += analog.subframes;
+            self.analog.extend_from_slice(&analog.values);
+        }
+    }
+
+    /// turn the frame major values into one contiguous run per marker and channel.
+    pub(crate) fn finish(&mut self) {
+        self.points = transpose(&self.points, self.markers, 1);
+        self.residuals = transpose(&self.residuals, self.markers, 1);
+        self.cameras = transpose(&self.cameras, self.markers, 1);
+
+        if self.channels == 0 || self.samples == 0 {
+            return;
+        }
+        let subframes = self.samples / self.frames.max(1);
+        self.analog = transpose(&self.analog, self.channels, subframes);
+    }
+
+    /// positions of the marker across all frames, labels with a subject prefix such as
+    /// `Subject:LASI` also match `LASI`.
+    pub fn marker(&self, label: &str) -> Option<&[[f32; 3]]> {
+        let marker = self.marker_index(label)?;
+        self.points
+            .get(marker * self.frames..(marker + 1) * self.frames)
+    }
+
+    /// residuals of the marker across all frames, NaN where it was not reconstructed.
+    pub fn marker_residuals(&self, label: &str) -> Option<&[f32]> {
+        let marker = self.marker_index(label)?;
+        self.residuals
+            .get(marker * self.frames..(marker + 1) * self.frames)
+    }
+
+    /// every sample of the channel, labels such as `Force.Fz1` also match `Fz1`.
+    pub fn analog_channel(&self, label: &str) -> Option<&[f32]> {
+        let channel = find_label(&self.analog_labels, label).filter(|v| *v < self.channels)?;
+        self.analog
+            .get(channel * self.samples..(channel + 1) * self.samples)
+    }
+
+    /// positions of all markers in the frame, counted from 0.
+    pub fn frame(&self, frame: usize) -> Option<Vec<[f32; 3]>> {
+        if frame >= self.frames {
+            return None;
+        }
+        let points = self.points.iter().skip(frame).step_by(self.frames);
+        Some(points.copied().collect())
+    }
+
+    fn marker_index(&self, label: &str) -> Option<usize> {
+        find_label(&self.point_labels, label).filter(|v| *v < self.markers)
+    }
+}
+
+/// frames x columns x `run` values, frame major, into one contiguous run of frames x `run`
+/// values per column.
+fn transpose<T: Copy + Default>(values: &[T], columns: usize, run: usize) -> Vec<T> {
+    let block = columns * run;
+    if block == 0 || values.is_empty() {
+        return values.to_vec();
+    }
+
+    let frames = values.len() / block;
+    let mut transposed = vec![T::default(); values.len()];
+    for (column, out) in transposed.chunks_mut(frames * run).enumerate() {
+        for (frame, values) in values.chunks_exact(block).enumerate() {
+            out[frame * run..(frame + 1) * run]
+                .copy_from_slice(&values[column * run..(column + 1) * run]);
+        }
+    }
+    transposed
+}
+
+/// `transpose` with a task per column.
+#[cfg(feature = "rayon")]
+fn transpose_parallel<T: Copy + Default + Send + Sync>(
+    values: &[T],
+    columns: usize,
+    run: usize,
+) -> Vec<T> {
+    use rayon::prelude::*;
+
+    let block = columns * run;
+    if block == 0 || values.is_empty() {
+        return values.to_vec();
+    }
+
+    let frames = values.len() / block;
+    let mut transposed = vec![T::default(); values.len()];
+    transposed
+        .par_chunks_mut(frames * run)
+        .enumerate()
+        .for_each(|(column, out)| {
+            for (frame, values) in values.chunks_exact(block).enumerate() {
+                out[frame * run..(frame + 1) * run]
+                    .copy_from_slice(&values[column * run..(column + 1) * run]);
+            }
+        });
+    transposed
+}
+
+/// `count` chunks of `width` values, padded with empty chunks once the values run out.
+#[cfg(feature = "rayon")]
+fn split_chunks<T>(values: &mut [T], width: usize, count: usize) -> Vec<&mut [T]> {
+    let mut chunks = values.chunks_mut(width.max(1)).collect::<Vec<_>>();
+    chunks.resize_with(count, Default::default);
+    chunks
+}
+
+/// exact match first, then the part after a `Subject:` or `Group.` prefix.
+pub(crate) fn find_label(labels: &[String], label: &str) -> Option<usize> {
+    labels.iter().position(|v| v == label).or_else(|| {
+        labels
+            .iter()
+            .position(|v| v.rsplit(&[':', '.'][..]).next() == Some(label))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn test_trial() -> anyhow::Result<()> {
+        let file = File::open("test_data/vicon_trial.c3d")?;
+        let adapter = C3dAdapter::new(file)?.construct()?;
+        let trial = Trial::from_adapter(&adapter)?;
+
+        assert_eq!(trial.first_frame, 1);
+        assert_eq!(trial.frames, 2129);
+        assert_eq!((trial.markers, trial.channels), (10, 22));
+        assert_eq!(trial.samples, 2129 * 4);
+        assert_eq!((trial.point_rate, trial.analog_rate), (250.0, 1000.0));
+        assert_eq!(trial.point_units, "mm");
+        assert_eq!(trial.analog_units[2], "N");
+
+        let frames = adapter.reader()?.collect::<Vec<_>>();
+        let marker = trial.marker("basketball2").unwrap();
+        assert_eq!(marker.len(), 2129);
+        for (position, (_, points, _)) in marker.iter().zip(frames.iter()) {
+            assert_eq!(*position, points.values[1].position());
+        }
+        let residuals = trial.marker_residuals("basketball2").unwrap();
+        assert_eq!(
+            Some(residuals[7]).filter(|v| !v.is_nan()),
+            frames[7].1.values[1].residual
+        );
+        assert!(trial.marker("LASI").is_none());
+
+        let positions = frames[7].1.values.iter().map(|v| v.position());
+        assert_eq!(trial.frame(7), Some(positions.collect()));
+        assert!(trial.frame(2129).is_none());
+
+        let fz = trial.analog_channel("Fz1").unwrap();
+        assert_eq!(Some(fz), trial.analog_channel("Force.Fz1"));
+        assert_eq!(fz.len(), 2129 * 4);
+        for (frame, (_, _, analog)) in frames.iter().enumerate() {
+            let analog = analog.as_ref().unwrap();
+            assert_eq!(&fz[frame * 4..frame * 4 + 4], analog.channel(2).unwrap());
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel() -> anyhow::Result<()> {
+        use crate::tests::synthetic;
+        use crate::{HeaderBlock, ParameterBlock, ParameterFormat, Point};
+        use std::io::{Cursor, Read};
+
+        let mut bytes = vec![];
+        File::open("test_data/vicon_trial.c3d")?.read_to_end(&mut bytes)?;
+
+        let assert_same = |trial: &Trial, parallel: &Trial| {
+            assert_eq!(parallel.frames, trial.frames);
+            assert_eq!(parallel.points, trial.points);
+            assert_eq!(parallel.cameras, trial.cameras);
+            assert_eq!(parallel.analog, trial.analog);
+            assert_eq!(
+                (parallel.channels, parallel.samples),
+                (trial.channels, trial.samples)
+            );
+            let bits = |v: &[f32]| v.iter().map(|v| v.to_bits()).collect::<Vec<_>>();
+            assert_eq!(bits(&parallel.residuals), bits(&trial.residuals));
+        };
+
+        // the whole file, then one cut inside the last frame.
+        for end in [bytes.len(), bytes.len() - 100].iter() {
+            let adapter = C3dAdapter::new(Cursor::new(&bytes[..*end]))?.construct()?;
+            assert_same(
+                &Trial::from_adapter(&adapter)?,
+                &adapter.read_all_parallel()?,
+            );
+        }
+
+        // points only, then analog only, over more than one chunk.
+        let header = HeaderBlock {
+            point_counts: 2,
+            frame_first: 1,
+            frame_last: 3000,
+            scale: -1.0,
+            frame_rate: 100.0,
+            ..Default::default()
+        };
+        let frames = (0..3000).map(|i| {
+            let point = Point {
+                x: i as f32,
+                y: 1.0,
+                residual: Some(0.5),
+                ..Default::default()
+            };
+            let points = PointData {
+                values: vec![point, Point::default()],
+            };
+            (points, None)
+        });
+        let adapter = synthetic(header, ParameterBlock::new(), frames)?;
+        let parallel = adapter.read_all_parallel()?;
+        assert_same(&Trial::from_adapter(&adapter)?, &parallel);
+        assert_eq!(parallel.points[2999], [2999.0, 1.0, 0.0]);
+
+        let header = HeaderBlock {
+            point_counts: 0,
+            analog_counts: 4,
+            analog_per_frame: 2,
+            ..header
+        };
+        let mut parameter = ParameterBlock::new();
+        parameter.insert("ANALOG", ParameterFormat::scalar("USED", 2_i16))?;
+        let frames = (0..3000).map(|i| {
+            let analog = AnalogData {
+                channels: 2,
+                subframes: 2,
+                values: vec![i as f32, 1.0, 2.0, 3.0],
+            };
+            (PointData { values: vec![] }, Some(analog))
+        });
+        let adapter = synthetic(header, parameter, frames)?;
+        let parallel = adapter.read_all_parallel()?;
+        assert_same(&Trial::from_adapter(&adapter)?, &parallel);
+        assert_eq!(parallel.analog[2 * 2999], 2999.0);
+
+        Ok(())
+    }
+}
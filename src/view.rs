@@ -0,0 +1,229 @@
+use crate::frame::FrameDecoder;
+use crate::{
+    frame_range, AnalogData, Baseline, C3dAdapter, HeaderBlock, ParameterBlock, ParserError, Point,
+    PointData,
+};
+use std::io::Cursor;
+use std::ops::RangeInclusive;
+
+/// A c3d file held in memory, e.g. a memory map. Frames are handed out as views into the
+/// borrowed bytes and only decoded when a value is asked for.
+pub struct C3dSlice<'a> {
+    pub header: HeaderBlock,
+    pub parameter: ParameterBlock,
+    bytes: &'a [u8],
+    decoder: FrameDecoder,
+    data_offset: usize,
+    frames: RangeInclusive<u32>,
+}
+
+impl<'a> C3dSlice<'a> {
+    pub fn new(bytes: &'a [u8]) -> Result<Self, ParserError> {
+        let adapter = C3dAdapter::new(Cursor::new(bytes))?.construct()?;
+        let header = adapter.header.ok_or(ParserError::MissingField)?;
+        let parameter = adapter.parameter.ok_or(ParserError::MissingField)?;
+
+        Ok(C3dSlice {
+            decoder: FrameDecoder::new(&header, &parameter),
+            data_offset: (header.data_start as usize).saturating_sub(1) * 512,
+            frames: frame_range(&header, &parameter),
+            header,
+            parameter,
+            bytes,
+        })
+    }
+
+    /// subtract the force plate baseline from every analog sample.
+    pub fn with_baseline(mut self, baseline: Baseline) -> Self {
+        self.decoder.baseline.replace(baseline);
+        self
+    }
+
+    /// first to last frame declared by the file.
+    pub fn frame_range(&self) -> RangeInclusive<u32> {
+        self.frames.clone()
+    }
+
+    /// None if the frame is outside of the trial or cut off by the end of the bytes.
+    pub fn frame(&self, frame: u32) -> Option<FrameView<'_>> {
+        if !self.frames.contains(&frame) {
+            return None;
+        }
+
+        let length = self.decoder.frame_length();
+        let start = self.data_offset + (frame - self.frames.start()) as usize * length;
+        let bytes = self.bytes.get(start..start + length)?;
+
+        Some(FrameView {
+            index: frame,
+            bytes,
+            decoder: &self.decoder,
+        })
+    }
+
+    /// every frame that is complete in the bytes.
+    pub fn frames(&self) -> FrameViews<'_> {
+        let first = *self.frames.start() as u64;
+        let mut back = *self.frames.end() as u64 + 1;
+
+        // a frame without points or analog samples takes no bytes at all.
+        let available = (self.bytes.len() as u64).saturating_sub(self.data_offset as u64);
+        if let Some(complete) = available.checked_div(self.decoder.frame_length() as u64) {
+            back = back.min(first + complete);
+        }
+
+        FrameViews {
+            slice: self,
+            front: first,
+            back: back.max(first),
+        }
+    }
+}
+
+/// One frame borrowed from a `C3dSlice`.
+#[derive(Clone, Copy)]
+pub struct FrameView<'a> {
+    index: u32,
+    bytes: &'a [u8],
+    decoder: &'a FrameDecoder,
+}
+
+impl<'a> FrameView<'a> {
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// the raw bytes of the frame.
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    pub fn point_counts(&self) -> usize {
+        self.decoder.point_counts()
+    }
+
+    pub fn point(&self, index: usize) -> Option<Point> {
+        self.decoder.point(self.bytes, index)
+    }
+
+    pub fn points(&self) -> impl Iterator<Item = Point> + 'a {
+        let (bytes, decoder) = (self.bytes, self.decoder);
+        (0..decoder.point_counts()).filter_map(move |idx| decoder.point(bytes, idx))
+    }
+
+    /// (channels, subframes) of the analog samples.
+    pub fn analog_layout(&self) -> (usize, usize) {
+        self.decoder.analog_layout()
+    }
+
+    /// a single analog sample, offset and scaled.
+    pub fn sample(&self, channel: usize, subframe: usize) -> Option<f32> {
+        self.decoder.sample(self.bytes, channel, subframe)
+    }
+
+    /// decode the whole frame, as the iterator of `C3dReader` would.
+    pub fn decode(&self) -> (PointData, Option<AnalogData>) {
+        self.decoder.decode(self.bytes)
+    }
+}
+
+/// Iterator over the frame views of a `C3dSlice`.
+pub struct FrameViews<'a> {
+    slice: &'a C3dSlice<'a>,
+    front: u64,
+    back: u64,
+}
+
+impl<'a> Iterator for FrameViews<'a> {
+    type Item = FrameView<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
+        let view = self.slice.frame(self.front as u32);
+        self.front += 1;
+        view
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back.saturating_sub(self.front) as usize;
+        (len, Some(len))
+    }
+}
+
+impl<'a> DoubleEndedIterator for FrameViews<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
+        self.back -= 1;
+        self.slice.frame(self.back as u32)
+    }
+}
+
+impl<'a> ExactSizeIterator for FrameViews<'a> {}
+
+/// Map the whole file into memory, pass the map to `C3dSlice::new`.
+///
+/// # Safety
+///
+/// The map is undefined behaviour once another process truncates or modifies the file.
+#[cfg(feature = "mmap")]
+pub unsafe fn map_file(file: &std::fs::File) -> Result<memmap2::Mmap, ParserError> {
+    Ok(memmap2::Mmap::map(file)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Read;
+
+    #[test]
+    fn test_frame_views() -> anyhow::Result<()> {
+        let mut bytes = vec![];
+        File::open("test_data/vicon_trial.c3d")?.read_to_end(&mut bytes)?;
+
+        let adapter = C3dAdapter::new(Cursor::new(&bytes[..]))?.construct()?;
+        let frames = adapter.reader()?.collect::<Vec<_>>();
+
+        let slice = C3dSlice::new(&bytes)?;
+        let views = slice.frames();
+        assert_eq!(views.len(), frames.len());
+
+        for (view, (idx, points, analog)) in views.zip(frames.iter()) {
+            assert_eq!(view.index(), *idx);
+            assert_eq!(view.points().collect::<Vec<_>>(), points.values);
+            assert_eq!(view.point(3), Some(points.values[3]));
+
+            let analog = analog.as_ref().unwrap();
+            assert_eq!(view.analog_layout(), (22, 4));
+            assert_eq!(view.sample(6, 3), analog.get(6, 3));
+            assert_eq!(view.sample(22, 0), None);
+            assert_eq!(view.decode(), (points.clone(), Some(analog.clone())));
+        }
+
+        let last = slice.frames().next_back().unwrap();
+        assert_eq!(last.index(), 2129);
+        assert!(slice.frame(2130).is_none());
+
+        // a cut off frame is not handed out.
+        let slice = C3dSlice::new(&bytes[..bytes.len() - 100])?;
+        assert_eq!(slice.frames().len(), 2128);
+        assert!(slice.frame(2129).is_none());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_map_file() -> anyhow::Result<()> {
+        let file = File::open("test_data/vicon_trial.c3d")?;
+        let map = unsafe { map_file(&file)? };
+        let slice = C3dSlice::new(&map)?;
+        assert_eq!(slice.frames().len(), 2129);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,738 @@
+use crate::{
+    next_group_id, read_record, AnalogData, AnalogFormat, AnalogScaling, GroupFormat, HeaderBlock,
+    ParamData, ParameterBlock, ParameterFormat, ParameterRecord, ParserError, PointData, Processor,
+};
+use std::io::prelude::*;
+use std::io::Cursor;
+use std::mem;
+use std::slice;
+
+/// Serialize header, parameter and data section into a c3d file.
+///
+/// The header and parameter block are written on construction, frames are then appended with
+/// `write_frame` and the data section is padded to a whole block by `finish`.
+pub struct C3dWriter<W: Write> {
+    handle: W,
+    header: HeaderBlock,
+    processor: Processor,
+    analog_scaling: AnalogScaling,
+    frame_buffer: Vec<u8>,
+    analog_buffer: Vec<f32>,
+    data_written: usize,
+}
+
+impl<W: Write> C3dWriter<W> {
+    pub fn new(
+        mut handle: W,
+        mut header: HeaderBlock,
+        mut parameter: ParameterBlock,
+    ) -> Result<Self, ParserError> {
+        // the offsets chaining the records don't depend on the values, so the size of the
+        // section can be known before DATA_START is patched.
+        let processor = parameter.processor();
+        let blocks = (4 + parameter_records(&parameter, processor)?.len()).div_ceil(512);
+        // the block count is a byte, and DATA_START is stored as an i16 as well.
+        if blocks > u8::MAX as usize || blocks + 2 > i16::MAX as usize {
+            return Err(ParserError::ParameterBlockOverflow(blocks));
+        }
+
+        let data_start = 2 + blocks as u16;
+        if let Some(param) = parameter.get_mut("POINT:DATA_START") {
+            param.parameter_data = ParamData::Int16(vec![data_start as i16]);
+        }
+
+        header.parameter_start = 2;
+        header.magic_word = 0x50;
+        header.data_start = data_start;
+
+        parameter.header.parameter_block_counts = blocks as u8;
+        parameter.set_processor(processor);
+
+        let mut parameter_bytes = vec![
+            parameter.header.reserved_one,
+            parameter.header.reserved_two,
+            parameter.header.parameter_block_counts,
+            parameter.header.magic_word,
+        ];
+        parameter_bytes.extend(parameter_records(&parameter, processor)?);
+        parameter_bytes.resize(blocks * 512, 0);
+
+        handle.write_all(header_bytes(&header.encode(processor)))?;
+        handle.write_all(&parameter_bytes)?;
+
+        let analog_scaling = AnalogScaling::from_parameter(&parameter);
+
+        Ok(C3dWriter {
+            handle,
+            header,
+            processor,
+            analog_scaling,
+            frame_buffer: vec![],
+            analog_buffer: vec![],
+            data_written: 0,
+        })
+    }
+
+    /// append a frame, points and analog values are converted back to their stored form.
+    pub fn write_frame(
+        &mut self,
+        points: &PointData,
+        analog: Option<&AnalogData>,
+    ) -> Result<(), ParserError> {
+        let processor = self.processor;
+        let point_scale = self.header.scale;
+        let is_float = point_scale <= 0.0;
+        let point_scale = point_scale.abs();
+        let analog_n = self.header.analog_counts as usize;
+
+        let (channels, subframes) = self
+            .analog_scaling
+            .layout(analog_n, self.header.analog_per_frame as usize);
+
+        // nothing is written unless the whole frame matches the layout.
+        if points.values.len() != self.header.point_counts as usize {
+            return Err(ParserError::FrameLayoutMismatch);
+        }
+        let analog = match analog {
+            Some(analog)
+                if analog.channels == channels
+                    && analog.subframes == subframes
+                    && analog.values.len() == analog_n =>
+            {
+                Some(analog)
+            }
+            None if analog_n == 0 => None,
+            _ => return Err(ParserError::FrameLayoutMismatch),
+        };
+
+        self.frame_buffer.clear();
+        for point in points.values.iter() {
+            // residual and camera mask are packed back into the fourth word, integer files
+            // have no room for the eighth camera as the word must stay positive.
+            let fourth = match point.residual {
+                None => -1_i32,
+                Some(residual) => {
+                    let residual = (residual / point_scale).round().clamp(0.0, 255.0) as i32;
+                    let cameras = if is_float {
+                        point.cameras.0
+                    } else {
+                        point.cameras.0 & 0x7f
+                    };
+                    (cameras as i32) << 8 | residual
+                }
+            };
+
+            let position = point.position();
+            if is_float {
+                for v in position.iter() {
+                    self.frame_buffer
+                        .extend_from_slice(&processor.write_f32(*v));
+                }
+                self.frame_buffer
+                    .extend_from_slice(&processor.write_f32(fourth as f32));
+            } else {
+                for v in position.iter() {
+                    let v = (v / point_scale).round() as i16;
+                    self.frame_buffer.extend_from_slice(&processor.write_i16(v));
+                }
+                self.frame_buffer
+                    .extend_from_slice(&processor.write_i16(fourth as i16));
+            }
+        }
+
+        if let Some(analog) = analog {
+            self.analog_buffer.clear();
+            self.analog_buffer.extend_from_slice(&analog.values);
+            self.analog_scaling
+                .revert(&mut self.analog_buffer, subframes);
+
+            // back to subframe by subframe with the channels interleaved.
+            let interleaved = (0..subframes).flat_map(|subframe| {
+                (0..channels).map(move |channel| channel * subframes + subframe)
+            });
+
+            let analog_buffer = &self.analog_buffer;
+            for v in interleaved.map(|idx| &analog_buffer[idx]) {
+                if is_float {
+                    self.frame_buffer
+                        .extend_from_slice(&processor.write_f32(*v));
+                } else if self.analog_scaling.format == AnalogFormat::Unsigned {
+                    self.frame_buffer
+                        .extend_from_slice(&processor.write_u16(v.round() as u16));
+                } else {
+                    self.frame_buffer
+                        .extend_from_slice(&processor.write_i16(v.round() as i16));
+                }
+            }
+        }
+
+        self.handle.write_all(&self.frame_buffer)?;
+        self.data_written += self.frame_buffer.len();
+
+        Ok(())
+    }
+
+    /// write every frame yielded by the iterator.
+    pub fn write_frames<I>(&mut self, frames: I) -> Result<(), ParserError>
+    where
+        I: IntoIterator<Item = (PointData, Option<AnalogData>)>,
+    {
+        for (points, analog) in frames {
+            self.write_frame(&points, analog.as_ref())?;
+        }
+        Ok(())
+    }
+
+    /// pad the data section to a whole block and hand back the underlying writer.
+    pub fn finish(mut self) -> Result<W, ParserError> {
+        let padding = (512 - self.data_written % 512) % 512;
+        self.handle.write_all(&vec![0_u8; padding])?;
+        self.handle.flush()?;
+        Ok(self.handle)
+    }
+}
+
+fn header_bytes(header: &HeaderBlock) -> &[u8] {
+    unsafe {
+        slice::from_raw_parts(
+            header as *const _ as *const u8,
+            mem::size_of::<HeaderBlock>(),
+        )
+    }
+}
+
+/// records in the order they were parsed, records added since come last ordered by group id
+/// and name. Parsed records whose values are unchanged are copied verbatim, and the bytes after
+/// the chain too once the whole section is unchanged.
+fn parameter_records(
+    parameter: &ParameterBlock,
+    processor: Processor,
+) -> Result<Vec<u8>, ParserError> {
+    let mut next_id = parameter.last_group_id();
+    let mut entries: Vec<(Option<usize>, i8, Option<&ParameterFormat>, &GroupFormat)> = vec![];
+
+    let mut names = parameter.groups.keys().collect::<Vec<_>>();
+    names.sort();
+    let groups = names.into_iter().map(|name| &parameter.groups[name]);
+    for group in groups.chain(parameter.duplicate_groups()) {
+        let id = if group.id > 0 {
+            group.id
+        } else {
+            next_id = next_group_id(next_id, &group.name)?;
+            next_id
+        };
+
+        entries.push((group.position, id, None, group));
+        entries.extend(
+            group
+                .params
+                .values()
+                .map(|param| (param.position, id, Some(param), group)),
+        );
+    }
+    // orphans keep the id they were read with, without a group record of their own.
+    let orphan_group = GroupFormat::default();
+    entries.extend(
+        parameter
+            .orphans()
+            .iter()
+            .map(|param| (param.position, param.group_id(), Some(param), &orphan_group)),
+    );
+    entries.sort_by_key(|&(position, id, param, _)| {
+        (
+            position.unwrap_or(usize::MAX),
+            id,
+            param.is_some(),
+            param.map(|v| v.name.as_str()),
+        )
+    });
+
+    let mut lossless = parameter
+        .tail
+        .as_ref()
+        .map(|v| v.records == entries.len())
+        .unwrap_or(false);
+    let mut records: Vec<(Vec<u8>, bool)> = vec![];
+    for (idx, (position, id, param, group)) in entries.into_iter().enumerate() {
+        let raw = match param {
+            Some(param) => reusable(param.raw.as_deref(), processor, |record| match record {
+                ParameterRecord::Parameter(parsed) => {
+                    parsed.id == id
+                        && parsed.name == param.name
+                        && parsed.dimensions == param.dimensions
+                        && parsed.parameter_data == param.parameter_data
+                        && parsed.description == param.description
+                        && parsed.locked == param.locked
+                }
+                _ => false,
+            }),
+            None => reusable(group.raw.as_deref(), processor, |record| match record {
+                ParameterRecord::Group {
+                    id: parsed_id,
+                    name,
+                    description,
+                    locked,
+                } => {
+                    *parsed_id as i8 == id
+                        && *name == group.name
+                        && *description == group.description
+                        && *locked == group.locked
+                }
+                _ => false,
+            }),
+        };
+
+        lossless &= raw.is_some() && position == Some(idx);
+        match (raw, param) {
+            (Some(raw), _) => records.push((raw.to_vec(), true)),
+            (None, Some(param)) => records.push((param_record(id, param, processor)?, false)),
+            (None, None) => records.push((group_record(id, group, processor)?, false)),
+        }
+    }
+
+    let counts = records.len();
+    for (idx, (record, copied)) in records.iter_mut().enumerate() {
+        let name_chars_size = record[0] as i8;
+        let offset_at = 2 + name_chars_size.unsigned_abs() as usize;
+        let offset = processor.read_i16([record[offset_at], record[offset_at + 1]]);
+
+        if idx + 1 == counts {
+            // the last record points nowhere to end the chain, a copied one may point past
+            // its gap to the zero padding.
+            if !*copied {
+                record[offset_at..offset_at + 2].copy_from_slice(&0_i16.to_le_bytes());
+            }
+        } else if offset == 0 {
+            // a copied record that used to end the chain now continues it.
+            let offset = (record.len() - offset_at) as i16;
+            record[offset_at..offset_at + 2].copy_from_slice(&processor.write_i16(offset));
+        }
+    }
+
+    let mut bytes = records.into_iter().flat_map(|v| v.0).collect::<Vec<_>>();
+    if let Some(tail) = parameter.tail.as_ref().filter(|_| lossless) {
+        bytes.extend_from_slice(&tail.bytes);
+    }
+    Ok(bytes)
+}
+
+/// the bytes a record was parsed from, if they still decode to the current values and the
+/// chain continues right after them.
+fn reusable(
+    raw: Option<&[u8]>,
+    processor: Processor,
+    matches: impl Fn(&ParameterRecord) -> bool,
+) -> Option<&[u8]> {
+    let raw = raw?;
+    let mut cursor = Cursor::new(raw);
+    let (record, next) = read_record(&mut cursor, processor).ok()??;
+    let end = next.unwrap_or(cursor.position());
+
+    if end == raw.len() as u64 && matches(&record) {
+        Some(raw)
+    } else {
+        None
+    }
+}
+
+/// Latin-1 as read back by the parser, UTF-8 when a character does not fit a byte.
+fn string_bytes(s: &str) -> Vec<u8> {
+    if s.chars().all(|c| (c as u32) <= 0xff) {
+        s.chars().map(|c| c as u32 as u8).collect()
+    } else {
+        s.as_bytes().to_vec()
+    }
+}
+
+/// lengths are stored in a byte or a word of the record, longer ones would wrap.
+fn checked_length(
+    name: &str,
+    field: &'static str,
+    length: usize,
+    max: usize,
+) -> Result<usize, ParserError> {
+    if length > max {
+        return Err(ParserError::RecordTooLong {
+            name: name.to_string(),
+            field,
+            length,
+        });
+    }
+    Ok(length)
+}
+
+fn record_head(name: &str, locked: bool, id: i8) -> Result<Vec<u8>, ParserError> {
+    let bytes = string_bytes(name);
+    let name_chars_size = checked_length(name, "name", bytes.len(), i8::MAX as usize)? as i8;
+    let name_chars_size = if locked {
+        -name_chars_size
+    } else {
+        name_chars_size
+    };
+
+    let mut record = vec![name_chars_size as u8, id as u8];
+    record.extend(bytes);
+    // placeholder for the offset to the next record.
+    record.extend_from_slice(&[0, 0]);
+    Ok(record)
+}
+
+fn record_tail(
+    mut record: Vec<u8>,
+    name: &str,
+    description: &str,
+    processor: Processor,
+) -> Result<Vec<u8>, ParserError> {
+    let description = string_bytes(description);
+    let length = checked_length(name, "description", description.len(), u8::MAX as usize)?;
+    record.push(length as u8);
+    record.extend(description);
+
+    let offset_at = 2 + (record[0] as i8).unsigned_abs() as usize;
+    let offset = checked_length(name, "record", record.len() - offset_at, i16::MAX as usize)?;
+    record[offset_at..offset_at + 2].copy_from_slice(&processor.write_i16(offset as i16));
+    Ok(record)
+}
+
+fn group_record(id: i8, group: &GroupFormat, processor: Processor) -> Result<Vec<u8>, ParserError> {
+    let record = record_head(&group.name, group.locked, -id)?;
+    record_tail(record, &group.name, &group.description, processor)
+}
+
+fn param_record(
+    id: i8,
+    param: &ParameterFormat,
+    processor: Processor,
+) -> Result<Vec<u8>, ParserError> {
+    let mut record = record_head(&param.name, param.locked, id)?;
+    let dimensions = checked_length(
+        &param.name,
+        "dimensions",
+        param.dimensions.len(),
+        u8::MAX as usize,
+    )?;
+
+    record.push(param.parameter_data.data_length() as u8);
+    record.push(dimensions as u8);
+    record.extend_from_slice(&param.dimensions);
+    record.extend(value_bytes(&param.parameter_data, processor));
+
+    record_tail(record, &param.name, &param.description, processor)
+}
+
+fn value_bytes(data: &ParamData, processor: Processor) -> Vec<u8> {
+    match data {
+        ParamData::Char(v) => v.iter().map(|c| *c as u32 as u8).collect(),
+        ParamData::Byte(v) => v.clone(),
+        ParamData::Int16(v) => v.iter().flat_map(|v| processor.write_i16(*v)).collect(),
+        ParamData::Float32(v) => v.iter().flat_map(|v| processor.write_f32(*v)).collect(),
+        ParamData::Unknown { bytes, .. } => bytes.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{C3dAdapter, CameraMask, Point};
+    use anyhow::Result;
+    use std::fs::File;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_round_trip() -> Result<()> {
+        let mut buf: Vec<u8> = vec![];
+        File::open("test_data/vicon_trial.c3d")?.read_to_end(&mut buf)?;
+
+        let adapter = C3dAdapter::new(Cursor::new(&buf[..]))?.construct()?;
+        let header = *adapter.header.as_ref().unwrap();
+        let parameter = adapter.parameter.clone().unwrap();
+
+        let mut writer = C3dWriter::new(Cursor::new(vec![]), header, parameter)?;
+        writer.write_frames(adapter.reader()?.map(|(_, p, a)| (p, a)))?;
+        let written = writer.finish()?.into_inner();
+
+        let data_start = (header.data_start as usize - 1) * 512;
+        let written_adapter = C3dAdapter::new(Cursor::new(&written[..]))?.construct()?;
+        let written_header = written_adapter.header.as_ref().unwrap();
+        let written_start = (written_header.data_start as usize - 1) * 512;
+
+        assert_eq!(&buf[data_start..], &written[written_start..]);
+
+        assert_eq!(
+            written_adapter.get_point_labels(),
+            adapter.get_point_labels()
+        );
+        let data_start_param = written_adapter
+            .parameter
+            .as_ref()
+            .unwrap()
+            .get("POINT:DATA_START")
+            .unwrap();
+        assert_eq!(
+            data_start_param.get_scalar::<i16>(),
+            Some(written_header.data_start as i16)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_integer_storage() -> Result<()> {
+        let header = HeaderBlock {
+            point_counts: 2,
+            analog_counts: 6,
+            frame_first: 1,
+            frame_last: 3,
+            scale: 0.1,
+            analog_per_frame: 3,
+            frame_rate: 100.0,
+            ..Default::default()
+        };
+
+        let mut parameter = ParameterBlock::new();
+        parameter.insert("POINT", ParameterFormat::scalar("DATA_START", 0_i16))?;
+        parameter.insert("ANALOG", ParameterFormat::scalar("USED", 2_i16))?;
+        parameter.insert(
+            "ANALOG",
+            ParameterFormat::new("SCALE", vec![2], ParamData::Float32(vec![0.5, 2.0])),
+        )?;
+        parameter.insert(
+            "ANALOG",
+            ParameterFormat::new("OFFSET", vec![2], ParamData::Int16(vec![10, -10])),
+        )?;
+
+        let frames = (0..3)
+            .map(|i| {
+                let points = PointData {
+                    values: vec![
+                        Point {
+                            x: i as f32,
+                            y: 1.5,
+                            z: -2.0,
+                            residual: Some(0.3),
+                            cameras: CameraMask(0b101),
+                        },
+                        Point::default(),
+                    ],
+                };
+                let analog = AnalogData {
+                    channels: 2,
+                    subframes: 3,
+                    values: vec![i as f32, 1.0, 2.0, -4.0, -6.0, -8.0],
+                };
+                (points, Some(analog))
+            })
+            .collect::<Vec<_>>();
+
+        let mut writer = C3dWriter::new(Cursor::new(vec![]), header, parameter)?;
+        writer.write_frames(frames.clone())?;
+        let written = writer.finish()?.into_inner();
+        assert_eq!(written.len() % 512, 0);
+
+        // stored as (value / scale + offset), subframe by subframe with channels interleaved.
+        let data_start = (written[16] as usize - 1) * 512;
+        let first_analog = data_start + 2 * 4 * 2;
+        let samples = written[first_analog..first_analog + 12]
+            .chunks_exact(2)
+            .map(|v| i16::from_le_bytes([v[0], v[1]]))
+            .collect::<Vec<_>>();
+        assert_eq!(samples, vec![10, -12, 12, -13, 14, -14]);
+
+        let adapter = C3dAdapter::new(Cursor::new(&written[..]))?.construct()?;
+        let read = adapter.reader()?.collect::<Vec<_>>();
+        assert_eq!(read.len(), frames.len());
+
+        for ((_, points, analog), (expected_points, expected_analog)) in
+            read.iter().zip(frames.iter())
+        {
+            let (p, e) = (points.values[0], expected_points.values[0]);
+            for (p, e) in p.position().iter().zip(e.position().iter()) {
+                assert!((p - e).abs() < 1e-4);
+            }
+            assert!((p.residual.unwrap() - 0.3).abs() < 1e-4);
+            assert_eq!(p.cameras, e.cameras);
+            assert_eq!(p.cameras.count(), 2);
+            assert!(!points.values[1].is_valid());
+            assert_eq!(
+                analog.as_ref().unwrap().values,
+                expected_analog.as_ref().unwrap().values
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejected_input() -> Result<()> {
+        let header = HeaderBlock {
+            point_counts: 1,
+            analog_counts: 2,
+            frame_first: 1,
+            frame_last: 1,
+            scale: -1.0,
+            analog_per_frame: 1,
+            frame_rate: 100.0,
+            ..Default::default()
+        };
+        let mut parameter = ParameterBlock::new();
+        parameter.insert("POINT", ParameterFormat::scalar("DATA_START", 0_i16))?;
+        parameter.insert("ANALOG", ParameterFormat::scalar("USED", 2_i16))?;
+
+        let points = PointData {
+            values: vec![Point::default()],
+        };
+        let analog = AnalogData {
+            channels: 2,
+            subframes: 1,
+            values: vec![1.0, 2.0],
+        };
+
+        // a frame that doesn't match the layout leaves nothing behind.
+        let mut writer = C3dWriter::new(Cursor::new(vec![]), header, parameter.clone())?;
+        assert!(writer.write_frame(&points, None).is_err());
+        assert!(writer
+            .write_frame(&PointData { values: vec![] }, Some(&analog))
+            .is_err());
+        writer.write_frame(&points, Some(&analog))?;
+        let written = writer.finish()?.into_inner();
+
+        let mut writer = C3dWriter::new(Cursor::new(vec![]), header, parameter.clone())?;
+        writer.write_frame(&points, Some(&analog))?;
+        assert_eq!(written, writer.finish()?.into_inner());
+
+        // lengths that don't fit their record are refused instead of wrapping.
+        let labels = ParameterFormat::strings("LABELS", &[&"X".repeat(200)[..]; 200]);
+        let long_name = ParameterFormat::scalar(&"N".repeat(128), 1_i16);
+        let mut long_description = ParameterFormat::scalar("RATE", 1_i16);
+        long_description.description = "D".repeat(256);
+
+        for (field, param) in [
+            ("record", labels),
+            ("name", long_name),
+            ("description", long_description),
+        ] {
+            let mut parameter = parameter.clone();
+            parameter.insert("POINT", param)?;
+            match C3dWriter::new(Cursor::new(vec![]), header, parameter) {
+                Err(ParserError::RecordTooLong { field: found, .. }) => assert_eq!(found, field),
+                _ => panic!("{} too long was accepted", field),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lossless_parameters() -> Result<()> {
+        let mut buf: Vec<u8> = vec![];
+        File::open("test_data/vicon_trial.c3d")?.read_to_end(&mut buf)?;
+
+        let adapter = C3dAdapter::new(Cursor::new(&buf[..]))?.construct()?;
+        let header = *adapter.header.as_ref().unwrap();
+        let parameter = adapter.parameter.clone().unwrap();
+        let data_start = (header.data_start as usize - 1) * 512;
+
+        // an unchanged section is written back byte for byte.
+        let writer = C3dWriter::new(Cursor::new(vec![]), header, parameter.clone())?;
+        let written = writer.finish()?.into_inner();
+        assert_eq!(&written[512..data_start], &buf[512..data_start]);
+
+        // changing a value keeps the ids and the order of every record.
+        let mut changed = parameter.clone();
+        changed.get_mut("POINT:RATE").unwrap().parameter_data = ParamData::Float32(vec![100.0]);
+        changed.insert("VENDOR", ParameterFormat::scalar("FLAG", 1_i16))?;
+        let writer = C3dWriter::new(Cursor::new(vec![]), header, changed)?;
+        let written = writer.finish()?.into_inner();
+        let read = C3dAdapter::new(Cursor::new(&written[..]))?.construct()?;
+        let read = read.parameter.as_ref().unwrap();
+
+        let order = |parameter: &ParameterBlock| {
+            parameter
+                .groups_in_order()
+                .into_iter()
+                .flat_map(|group| {
+                    std::iter::once((group.id, group.name.clone())).chain(
+                        group
+                            .params_in_order()
+                            .into_iter()
+                            .map(|v| (v.group_id(), v.name.clone())),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut expected = order(&parameter);
+        let vendor = expected.iter().map(|v| v.0).max().unwrap() + 1;
+        expected.extend(vec![
+            (vendor, "VENDOR".to_string()),
+            (vendor, "FLAG".to_string()),
+        ]);
+        assert_eq!(order(read), expected);
+        assert_eq!(
+            read.get("POINT:RATE").unwrap().get_scalar(),
+            Some(100.0_f32)
+        );
+        assert_eq!(
+            read.get("POINT:LABELS").unwrap().as_strings(),
+            adapter.get_point_labels()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_parameter_type() -> Result<()> {
+        // a vendor parameter of 3 byte elements followed by a known one.
+        let mut block = vec![1, 0x50, 1, 84];
+        block.extend_from_slice(&[6, (-1_i8) as u8]);
+        block.extend_from_slice(b"VENDOR");
+        block.extend_from_slice(&3_i16.to_le_bytes());
+        block.push(0);
+        block.extend_from_slice(&[3, 1]);
+        block.extend_from_slice(b"RGB");
+        block.extend_from_slice(&12_i16.to_le_bytes());
+        block.extend_from_slice(&[3, 1, 2, 255, 0, 0, 0, 255, 0, 0]);
+        block.extend_from_slice(&[4, 1]);
+        block.extend_from_slice(b"GAIN");
+        block.extend_from_slice(&0_i16.to_le_bytes());
+        block.extend_from_slice(&[4, 0]);
+        block.extend_from_slice(&2.5_f32.to_le_bytes());
+        block.push(0);
+        block.resize(512, 0);
+
+        let mut header = vec![0_u8; 512];
+        header[..2].copy_from_slice(&[2, 0x50]);
+        header[16..18].copy_from_slice(&3_u16.to_le_bytes());
+        let mut file = header;
+        file.extend_from_slice(&block);
+
+        let adapter = C3dAdapter::new(Cursor::new(&file[..]))?.construct()?;
+        let parameter = adapter.parameter.clone().unwrap();
+        let rgb = parameter.get("VENDOR:RGB").unwrap().parameter_data.clone();
+        assert_eq!(
+            rgb,
+            ParamData::Unknown {
+                data_length: 3,
+                bytes: vec![255, 0, 0, 0, 255, 0],
+            }
+        );
+        assert_eq!(rgb.len(), 2);
+
+        let header = *adapter.header.as_ref().unwrap();
+        let writer = C3dWriter::new(Cursor::new(vec![]), header, parameter.clone())?;
+        let written = writer.finish()?.into_inner();
+        assert_eq!(&written[512..1024], &block[..]);
+
+        // the undecoded values survive once the section is rebuilt.
+        let mut parameter = parameter;
+        parameter.insert("VENDOR", ParameterFormat::scalar("USED", 1_i16))?;
+        let writer = C3dWriter::new(Cursor::new(vec![]), header, parameter)?;
+        let written = writer.finish()?.into_inner();
+        let read = C3dAdapter::new(Cursor::new(&written[..]))?.construct()?;
+        let read = read.parameter.as_ref().unwrap();
+        assert_eq!(read.get("VENDOR:RGB").unwrap().parameter_data, rgb);
+        assert_eq!(read.get("VENDOR:GAIN").unwrap().get_scalar(), Some(2.5_f32));
+        assert_eq!(read.get("VENDOR:USED").unwrap().get_scalar(), Some(1_i16));
+
+        Ok(())
+    }
+}